@@ -1,6 +1,6 @@
 use commands::Difference;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Position {
     pub x: u8,
     pub y: u8,
@@ -8,12 +8,16 @@ pub struct Position {
 }
 
 impl Position {
+    pub fn new(x: u8, y: u8, z: u8) -> Position {
+        Self { x, y, z }
+    }
+
     pub fn zero() -> Position {
         Self { x: 0, y: 0, z: 0 }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Bot {
     pub index: usize,
     pub seeds: Vec<u8>,
@@ -37,6 +41,10 @@ impl Bot {
         }
     }
 
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
     pub fn apply_position_diff(&mut self, diff: &Difference) {
         self.position = get_position_by_diff(&self.position, diff);
     }
@@ -49,18 +57,18 @@ impl Bot {
 pub fn get_position_by_diff(position: &Position, diff: &Difference) -> Position {
     let mut position = position.clone();
     if diff.dx < 0 {
-        position.x -= diff.dx.abs() as u8;
+        position.x -= diff.dx.unsigned_abs();
     } else {
         position.x += diff.dx as u8;
     }
 
     if diff.dy < 0 {
-        position.y -= diff.dy.abs() as u8;
+        position.y -= diff.dy.unsigned_abs();
     } else {
         position.y += diff.dy as u8;
     }
     if diff.dz < 0 {
-        position.z -= diff.dz.abs() as u8;
+        position.z -= diff.dz.unsigned_abs();
     } else {
         position.z += diff.dz as u8;
     }
@@ -7,21 +7,18 @@ pub trait Clen {
 }
 
 fn mlen(values: &[i8]) -> u32 {
-    values
-        .iter()
-        .map(|value| value.abs() as u32)
-        .fold(0, |acc, value| acc + value as u32)
+    values.iter().map(|value| value.unsigned_abs() as u32).sum()
 }
 
 fn clen(values: &[i8]) -> u32 {
     values
         .iter()
-        .map(|value| value.abs() as u32)
+        .map(|value| value.unsigned_abs() as u32)
         .max()
         .unwrap_or_default()
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DifferenceKind {
     Near,
     Far,
@@ -29,7 +26,7 @@ pub enum DifferenceKind {
     LongLinear,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Difference {
     pub dx: i8,
     pub dy: i8,
@@ -41,6 +38,26 @@ impl Difference {
     pub fn new(dx: i8, dy: i8, dz: i8, kind: DifferenceKind) -> Self {
         Self { dx, dy, dz, kind }
     }
+
+    pub fn near(dx: i8, dy: i8, dz: i8) -> Self {
+        Self::new(dx, dy, dz, DifferenceKind::Near)
+    }
+
+    pub fn far(dx: i8, dy: i8, dz: i8) -> Self {
+        Self::new(dx, dy, dz, DifferenceKind::Far)
+    }
+
+    pub fn short_linear(dx: i8, dy: i8, dz: i8) -> Self {
+        Self::new(dx, dy, dz, DifferenceKind::ShortLinear)
+    }
+
+    pub fn long_linear(dx: i8, dy: i8, dz: i8) -> Self {
+        Self::new(dx, dy, dz, DifferenceKind::LongLinear)
+    }
+
+    pub fn negated(&self) -> Self {
+        Self::new(-self.dx, -self.dy, -self.dz, self.kind)
+    }
 }
 
 impl Mlen for Difference {
@@ -64,6 +81,10 @@ pub fn read_fd(buffer: &[u8; 3]) -> Difference {
     )
 }
 
+pub fn write_fd(fd: &Difference) -> [u8; 3] {
+    [(fd.dx + 30) as u8, (fd.dy + 30) as u8, (fd.dz + 30) as u8]
+}
+
 pub fn read_nd(current: u8) -> Difference {
     let nd = current >> 3;
 
@@ -74,6 +95,12 @@ pub fn read_nd(current: u8) -> Difference {
     Difference::new(dx, dy, dz, DifferenceKind::Near)
 }
 
+/// Five high bits of a command byte holding `nd`.
+pub fn write_nd(nd: &Difference) -> u8 {
+    let nd = (nd.dx + 1) * 9 + (nd.dy + 1) * 3 + (nd.dz + 1);
+    (nd as u8) << 3
+}
+
 fn read_ld(a: u8, i: u8, delta: u8) -> (i8, i8, i8) {
     let delta = i as i8 - delta as i8;
     match a {
@@ -84,6 +111,19 @@ fn read_ld(a: u8, i: u8, delta: u8) -> (i8, i8, i8) {
     }
 }
 
+fn write_ld(d: &Difference, delta: u8) -> anyhow::Result<(u8, u8)> {
+    let (a, value) = match (d.dx, d.dy, d.dz) {
+        (dx, 0, 0) if dx != 0 => (0b01, dx),
+        (0, dy, 0) if dy != 0 => (0b10, dy),
+        (0, 0, dz) if dz != 0 => (0b11, dz),
+        _ => return Err(anyhow::anyhow!("{d:?} is not a linear difference")),
+    };
+    if value.unsigned_abs() > delta {
+        return Err(anyhow::anyhow!("{d:?} is longer than {delta}"));
+    }
+    Ok((a, (value + delta as i8) as u8))
+}
+
 pub struct Sld;
 
 impl Sld {
@@ -91,6 +131,11 @@ impl Sld {
         let (dx, dy, dz) = read_ld(a, i, 5);
         Difference::new(dx, dy, dz, DifferenceKind::ShortLinear)
     }
+
+    /// Axis and index of a short linear difference, the inverse of `read`.
+    pub fn write(sld: &Difference) -> anyhow::Result<(u8, u8)> {
+        write_ld(sld, 5)
+    }
 }
 
 pub struct Lld;
@@ -100,4 +145,9 @@ impl Lld {
         let (dx, dy, dz) = read_ld(a, i, 15);
         Difference::new(dx, dy, dz, DifferenceKind::LongLinear)
     }
+
+    /// Axis and index of a long linear difference, the inverse of `read`.
+    pub fn write(lld: &Difference) -> anyhow::Result<(u8, u8)> {
+        write_ld(lld, 15)
+    }
 }
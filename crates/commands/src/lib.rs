@@ -2,7 +2,7 @@ mod distance;
 
 pub use distance::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Command {
     Halt,
     Wait,
@@ -27,7 +27,7 @@ pub fn read_bits(byte: u8, bit_count: u8) -> u8 {
     byte & mask
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SMove {
     pub lld: Difference,
 }
@@ -41,9 +41,14 @@ impl SMove {
             lld: Lld::read(a, i),
         }
     }
+
+    pub fn write(&self) -> anyhow::Result<[u8; 2]> {
+        let (a, i) = Lld::write(&self.lld)?;
+        Ok([a << 4 | 0b0100, i])
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LMove {
     pub sld1: Difference,
     pub sld2: Difference,
@@ -61,9 +66,15 @@ impl LMove {
             sld2: Sld::read(a2, i2),
         }
     }
+
+    pub fn write(&self) -> anyhow::Result<[u8; 2]> {
+        let (a1, i1) = Sld::write(&self.sld1)?;
+        let (a2, i2) = Sld::write(&self.sld2)?;
+        Ok([a2 << 6 | a1 << 4 | 0b1100, i2 << 4 | i1])
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FusionP {
     pub nd: Difference,
 }
@@ -73,9 +84,13 @@ impl FusionP {
         let nd = read_nd(current);
         Self { nd }
     }
+
+    pub fn write(&self) -> u8 {
+        write_nd(&self.nd) | 0b111
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FusionS {
     pub nd: Difference,
 }
@@ -85,8 +100,12 @@ impl FusionS {
         let nd = read_nd(current);
         Self { nd }
     }
+
+    pub fn write(&self) -> u8 {
+        write_nd(&self.nd) | 0b110
+    }
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Fission {
     pub nd: Difference,
     pub m: u8,
@@ -98,9 +117,13 @@ impl Fission {
         let m = buffer[0];
         Self { nd, m }
     }
+
+    pub fn write(&self) -> [u8; 2] {
+        [write_nd(&self.nd) | 0b101, self.m]
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Fill {
     pub nd: Difference,
 }
@@ -111,9 +134,13 @@ impl Fill {
             nd: read_nd(current),
         }
     }
+
+    pub fn write(&self) -> u8 {
+        write_nd(&self.nd) | 0b011
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Void {
     pub nd: Difference,
 }
@@ -124,9 +151,13 @@ impl Void {
             nd: read_nd(current),
         }
     }
+
+    pub fn write(&self) -> u8 {
+        write_nd(&self.nd) | 0b010
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GFill {
     pub nd: Difference,
     pub fd: Difference,
@@ -138,9 +169,14 @@ impl GFill {
         let fd = read_fd(buffer);
         Self { nd, fd }
     }
+
+    pub fn write(&self) -> [u8; 4] {
+        let [x, y, z] = write_fd(&self.fd);
+        [write_nd(&self.nd) | 0b001, x, y, z]
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GVoid {
     pub nd: Difference,
     pub fd: Difference,
@@ -152,6 +188,11 @@ impl GVoid {
         let fd = read_fd(buffer);
         Self { nd, fd }
     }
+
+    pub fn write(&self) -> [u8; 4] {
+        let [x, y, z] = write_fd(&self.fd);
+        [write_nd(&self.nd), x, y, z]
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,24 @@
+use anyhow::anyhow;
+use mdl::Family;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// `generate <family> <r> <seed> <model.mdl|model.vox>`
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    let [family, r, seed, output] = args else {
+        return Err(anyhow!(
+            "Usage: console generate <family> <r> <seed> <model.mdl|model.vox>"
+        ));
+    };
+
+    let matrix = mdl::generate(family.parse::<Family>()?, r.parse()?, seed.parse()?)?;
+    let path = Path::new(output);
+    let mut writer = BufWriter::new(File::create(path)?);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("vox") => mdl::write_vox(&mut writer, &matrix)?,
+        _ => mdl::write_matrix(&mut writer, &matrix)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
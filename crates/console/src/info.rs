@@ -0,0 +1,20 @@
+use anyhow::anyhow;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// `info <model.mdl|model.vox>`
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    let [model] = args else {
+        return Err(anyhow!("Usage: console info <model.mdl|model.vox>"));
+    };
+
+    let path = Path::new(model);
+    let mut reader = BufReader::new(File::open(path)?);
+    let matrix = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("vox") => mdl::read_vox(&mut reader)?,
+        _ => mdl::read_matrix(&mut reader)?,
+    };
+    print!("{}", matrix.report());
+    Ok(())
+}
@@ -1,7 +1,20 @@
-use state::StateCommand;
 use std::io::Cursor;
 
+mod generate;
+mod info;
+mod submit;
+
 fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("generate") => generate::run(&args[1..]),
+        Some("info") => info::run(&args[1..]),
+        Some("submit") => submit::run(&args[1..]),
+        _ => replay(),
+    }
+}
+
+fn replay() -> anyhow::Result<()> {
     let model_data = include_bytes!("../../../data/FA001_tgt.mdl");
     let nbt_data = include_bytes!("../../../data/FA001.nbt");
     let mut matrix_reader = Cursor::new(model_data);
@@ -13,28 +26,16 @@ fn main() -> anyhow::Result<()> {
     let empty_matrix = mdl::Matrix::new(matrix.r);
     let mut state = state::State::new(10, empty_matrix);
 
-    let mut commands = &commands[..];
-
-    while commands.len() > 0 {
-        let command = &commands[0];
-        commands = &commands[1..];
-        command.apply(&mut state)?;
-        let delta = 0;
-        commands = &commands[delta..];
-        state.end_step();
-    }
+    state.execute(&commands)?;
 
     println!("{:?}", state.energy_spend_type);
     println!("energy {:?}", state.energy);
     println!("{:?}", state.bots[0]);
-    assert_eq!(matrix, state.matrix);
-    Ok(())
+    state.check_target(&matrix)
 }
 
 #[cfg(test)]
 mod tests {
-    use glob;
-    use state::StateCommand;
     use std::fs::File;
     use std::io::BufReader;
     use std::path::{Path, PathBuf};
@@ -46,13 +47,11 @@ mod tests {
         let pattern = "/Users/axel/Downloads/problemsF/FA*.nbt";
         let folder = Path::new("/Users/axel/Downloads/problemsF/");
 
-        for entry in glob::glob(pattern)? {
-            if let Ok(path) = entry {
-                match run_one(&path, &folder) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        eprintln!("{}", e);
-                    }
+        for path in glob::glob(pattern)?.flatten() {
+            match run_one(&path, folder) {
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("{}", e);
                 }
             }
         }
@@ -65,7 +64,7 @@ mod tests {
         println!("Running {:?}", path);
 
         if let Some(stem) = path.file_stem() {
-            let model_file = folder.join(format!("{}_tgt.mdl", stem.to_string_lossy()).to_string());
+            let model_file = folder.join(format!("{}_tgt.mdl", stem.to_string_lossy()));
             println!("With model file {:?}", model_file);
             if model_file.exists() {
                 let model_data = File::open(model_file)?;
@@ -78,17 +77,8 @@ mod tests {
                 let empty_matrix = mdl::Matrix::new(matrix.r);
                 let mut state = state::State::new(10, empty_matrix);
 
-                let mut commands = &commands[..];
-
-                while commands.len() > 0 {
-                    let command = &commands[0];
-                    commands = &commands[1..];
-                    command.apply(&mut state)?;
-                    let delta = 0;
-                    commands = &commands[delta..];
-                    state.end_step();
-                }
-                assert_eq!(matrix, state.matrix);
+                state.execute(&commands)?;
+                state.check_target(&matrix)?;
             }
         }
         Ok(())
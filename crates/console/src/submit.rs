@@ -0,0 +1,163 @@
+use anyhow::anyhow;
+use mdl::Matrix;
+use sha2::{Digest, Sha256};
+use solvers::store::Store;
+use state::State;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{BufReader, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Bots available in the full round.
+const MAX_BOTS: u8 = 40;
+
+/// `submit <problems> <store> <defaults> <archive.zip>`
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    let [problems, store, defaults, archive] = args else {
+        return Err(anyhow!(
+            "Usage: console submit <problems> <store> <defaults> <archive.zip>"
+        ));
+    };
+
+    let hash = build_archive(
+        Path::new(problems),
+        &Store::open(store)?,
+        Path::new(defaults),
+        Path::new(archive),
+    )?;
+    println!("{archive} sha256 {hash}");
+    Ok(())
+}
+
+/// Writes one `<problem>.nbt` per problem of the `problems` directory into
+/// the zip at `archive`, taking the best trace of the store and falling back
+/// to `<defaults>/<problem>.nbt`. Every trace is simulated first, nothing is
+/// written unless all of them halt with the target model. Returns the
+/// SHA-256 of the archive in hex.
+pub fn build_archive(
+    problems: &Path,
+    store: &Store,
+    defaults: &Path,
+    archive: &Path,
+) -> anyhow::Result<String> {
+    let mut traces = vec![];
+
+    for problem in problem_ids(problems)? {
+        let path = match store.best(&problem) {
+            Some(entry) => store.root().join(&entry.trace_file),
+            None => defaults.join(format!("{problem}.nbt")),
+        };
+        let trace = fs::read(&path)
+            .map_err(|e| anyhow!("No trace for {problem} at {}: {e}", path.display()))?;
+        let energy = verify(problems, &problem, &trace)
+            .map_err(|e| anyhow!("Invalid trace for {problem} at {}: {e}", path.display()))?;
+        println!("{problem} energy {energy}");
+        traces.push((problem, trace));
+    }
+
+    let mut zip = ZipWriter::new(File::create(archive)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (problem, trace) in &traces {
+        zip.start_file(format!("{problem}.nbt"), options)?;
+        zip.write_all(trace)?;
+    }
+    zip.finish()?;
+
+    Ok(hex::encode(Sha256::digest(fs::read(archive)?)))
+}
+
+/// Problem ids, e.g. `FA001`, of the `_src.mdl` and `_tgt.mdl` files in
+/// `directory`.
+fn problem_ids(directory: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let mut ids = BTreeSet::new();
+    for entry in fs::read_dir(directory)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if let Some(id) = name
+            .strip_suffix("_src.mdl")
+            .or_else(|| name.strip_suffix("_tgt.mdl"))
+        {
+            ids.insert(id.to_string());
+        }
+    }
+    Ok(ids)
+}
+
+/// Simulates `trace` on `problem`, returning the energy it spends.
+fn verify(problems: &Path, problem: &str, trace: &[u8]) -> anyhow::Result<i64> {
+    let source = read_model(&problems.join(format!("{problem}_src.mdl")))?;
+    let target = read_model(&problems.join(format!("{problem}_tgt.mdl")))?;
+    let r = source
+        .as_ref()
+        .or(target.as_ref())
+        .map(|matrix| matrix.r)
+        .ok_or_else(|| anyhow!("No model"))?;
+
+    let commands = nbt::read_commands(&mut BufReader::new(trace))?;
+    let mut state = State::new(MAX_BOTS, source.unwrap_or_else(|| Matrix::new(r)));
+    state.execute(&commands)?;
+    state.check_target(&target.unwrap_or_else(|| Matrix::new(r)))?;
+    Ok(state.energy)
+}
+
+/// Reads the model at `path`, missing source or target models are empty.
+fn read_model(path: &Path) -> anyhow::Result<Option<Matrix>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(mdl::read_matrix(&mut BufReader::new(File::open(
+        path,
+    )?))?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_build_archive_from_default_traces() -> anyhow::Result<()> {
+        let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data");
+        let directory = tempfile::tempdir()?;
+        let store = Store::open(directory.path().join("store"))?;
+        let archive = directory.path().join("submission.zip");
+
+        let hash = build_archive(&data, &store, &data, &archive)?;
+
+        assert_eq!(64, hash.len());
+        let mut zip = zip::ZipArchive::new(File::open(&archive)?)?;
+        let mut names = zip.file_names().map(str::to_string).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            vec!["FA001.nbt", "FA002.nbt", "FA003.nbt", "FA004.nbt"],
+            names
+        );
+
+        let mut trace = vec![];
+        zip.by_name("FA001.nbt")?.read_to_end(&mut trace)?;
+        assert_eq!(fs::read(data.join("FA001.nbt"))?, trace);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_archive_rejects_invalid_trace() -> anyhow::Result<()> {
+        let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data");
+        let directory = tempfile::tempdir()?;
+        let store = Store::open(directory.path().join("store"))?;
+        let defaults = directory.path().join("defaults");
+        fs::create_dir(&defaults)?;
+        for problem in ["FA001", "FA002", "FA003"] {
+            fs::copy(
+                data.join(format!("{problem}.nbt")),
+                defaults.join(format!("{problem}.nbt")),
+            )?;
+        }
+        fs::copy(data.join("FA001.nbt"), defaults.join("FA004.nbt"))?;
+        let archive = directory.path().join("submission.zip");
+
+        assert!(build_archive(&data, &store, &defaults, &archive).is_err());
+        assert!(!archive.exists());
+        Ok(())
+    }
+}
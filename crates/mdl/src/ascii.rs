@@ -0,0 +1,164 @@
+use crate::{CellState, Matrix};
+use std::fmt;
+use std::str::FromStr;
+
+/// Text form of a model: one grid per y layer from the floor up, separated
+/// by blank lines. Rows go along x and columns along z, `#` is Full and `.`
+/// is Void. Layers missing above the last one are Void, and surrounding
+/// whitespace is ignored so models can be indented inline in tests.
+///
+/// ```text
+/// ...
+/// .#.
+/// ...
+///
+/// ...
+/// .#.
+/// ...
+/// ```
+impl FromStr for Matrix {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let lines = text.trim().lines().map(str::trim).collect::<Vec<_>>();
+        let layers = lines
+            .split(|line| line.is_empty())
+            .filter(|layer| !layer.is_empty())
+            .collect::<Vec<_>>();
+
+        let r = layers.first().map_or(0, |layer| layer.len());
+        if layers.len() > r {
+            return Err(anyhow::anyhow!(
+                "{} layers for a resolution of {r}",
+                layers.len()
+            ));
+        }
+
+        let mut matrix = Matrix::new(r);
+        for (y, layer) in layers.iter().enumerate() {
+            if layer.len() != r {
+                return Err(anyhow::anyhow!(
+                    "Layer {y} has {} rows, expected {r}",
+                    layer.len()
+                ));
+            }
+            for (x, row) in layer.iter().enumerate() {
+                if row.chars().count() != r {
+                    return Err(anyhow::anyhow!(
+                        "Row {x} of layer {y} isn't {r} wide: {row:?}"
+                    ));
+                }
+                for (z, voxel) in row.chars().enumerate() {
+                    match voxel {
+                        '#' => matrix.set(x, y, z, CellState::Fill),
+                        '.' => {}
+                        _ => return Err(anyhow::anyhow!("Unknown voxel {voxel:?} in {row:?}")),
+                    }
+                }
+            }
+        }
+
+        Ok(matrix)
+    }
+}
+
+/// Prints every layer in the form `from_str` reads.
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.r {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for x in 0..self.r {
+                let row = (0..self.r)
+                    .map(|z| if self.is_filled(x, y, z) { '#' } else { '.' })
+                    .collect::<String>();
+                writeln!(f, "{row}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_round_trip() -> anyhow::Result<()> {
+        let matrix: Matrix = "
+            ...
+            .##
+            ...
+
+            ...
+            .#.
+            ...
+        "
+        .parse()?;
+
+        assert!(matrix.is_filled(1, 0, 1) && matrix.is_filled(1, 0, 2));
+        assert!(matrix.is_filled(1, 1, 1));
+        assert_eq!(
+            3,
+            matrix
+                .iter()
+                .filter(|cell| cell.state == CellState::Fill)
+                .count()
+        );
+        assert_eq!(matrix, matrix.to_string().parse()?);
+        assert!("..\n.#.".parse::<Matrix>().is_err());
+        assert!("..\n.x".parse::<Matrix>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ascii_layers_go_up() -> anyhow::Result<()> {
+        // An L-shaped column and a voxel floating above it.
+        let model: Matrix = "
+            ......
+            ......
+            ..#...
+            ......
+            ......
+            ......
+
+            ......
+            ......
+            ..#...
+            ......
+            ......
+            ......
+
+            ......
+            ......
+            ..#...
+            ..#...
+            ..#...
+            ......
+
+            ......
+            ......
+            ......
+            ......
+            ......
+            ......
+
+            ......
+            .#....
+            ......
+            ......
+            ......
+            ......
+        "
+        .parse()?;
+
+        let mut expected = Matrix::new(6);
+        for (x, y, z) in [(2, 0, 2), (2, 1, 2), (2, 2, 2), (3, 2, 2), (4, 2, 2)] {
+            expected.set(x, y, z, CellState::Fill);
+        }
+        expected.set(1, 4, 1, CellState::Fill);
+        assert_eq!(expected, model);
+        Ok(())
+    }
+}
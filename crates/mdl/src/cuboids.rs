@@ -0,0 +1,174 @@
+use crate::Matrix;
+
+/// Box of voxels between two corners, both included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cuboid {
+    pub min: [usize; 3],
+    pub max: [usize; 3],
+}
+
+impl Cuboid {
+    pub fn volume(&self) -> usize {
+        (0..3).map(|axis| self.side(axis)).product()
+    }
+
+    /// Voxels along `axis`.
+    pub fn side(&self, axis: usize) -> usize {
+        self.max[axis] - self.min[axis] + 1
+    }
+
+    pub fn contains(&self, position: [usize; 3]) -> bool {
+        (0..3).all(|axis| (self.min[axis]..=self.max[axis]).contains(&position[axis]))
+    }
+
+    pub fn voxels(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        (self.min[0]..=self.max[0]).flat_map(move |x| {
+            (self.min[1]..=self.max[1])
+                .flat_map(move |y| (self.min[2]..=self.max[2]).map(move |z| [x, y, z]))
+        })
+    }
+}
+
+/// Orders in which boxes grow from their corner, z then x then y first.
+const GROWTH_ORDERS: [[usize; 3]; 6] = [
+    [2, 0, 1],
+    [0, 2, 1],
+    [2, 1, 0],
+    [0, 1, 2],
+    [1, 2, 0],
+    [1, 0, 2],
+];
+
+impl Matrix {
+    /// Splits the Full voxels into non-overlapping cuboids. Scanning from
+    /// the floor up, every voxel not covered yet starts a box grown one axis
+    /// at a time while it stays Full and uncovered, keeping the largest of
+    /// every order of the axes. Sides are capped at `max_side` voxels, 30
+    /// fits a `GFill` region.
+    pub fn cuboids(&self, max_side: Option<usize>) -> Vec<Cuboid> {
+        let r = self.r;
+        let max_side = max_side.unwrap_or(r).max(1);
+        let mut covered = vec![false; r * r * r];
+        let mut cuboids = vec![];
+
+        for y in 0..r {
+            for x in 0..r {
+                for z in 0..r {
+                    if covered[x * r * r + y * r + z] || !self.is_filled(x, y, z) {
+                        continue;
+                    }
+
+                    let cuboid = GROWTH_ORDERS
+                        .iter()
+                        .map(|order| self.grow([x, y, z], order, max_side, &covered))
+                        .reduce(|best, cuboid| {
+                            if cuboid.volume() > best.volume() {
+                                cuboid
+                            } else {
+                                best
+                            }
+                        })
+                        .unwrap();
+
+                    for [x, y, z] in cuboid.voxels() {
+                        covered[x * r * r + y * r + z] = true;
+                    }
+                    cuboids.push(cuboid);
+                }
+            }
+        }
+
+        cuboids
+    }
+
+    /// Grows a box from `corner` towards larger coordinates, one axis after
+    /// the other in `order`.
+    fn grow(
+        &self,
+        corner: [usize; 3],
+        order: &[usize; 3],
+        max_side: usize,
+        covered: &[bool],
+    ) -> Cuboid {
+        let r = self.r;
+        let free =
+            |[x, y, z]: [usize; 3]| self.is_filled(x, y, z) && !covered[x * r * r + y * r + z];
+        let mut cuboid = Cuboid {
+            min: corner,
+            max: corner,
+        };
+
+        for &axis in order {
+            while cuboid.max[axis] + 1 < r && cuboid.side(axis) < max_side {
+                let mut slab = cuboid;
+                slab.min[axis] = cuboid.max[axis] + 1;
+                slab.max[axis] = cuboid.max[axis] + 1;
+                if !slab.voxels().all(free) {
+                    break;
+                }
+                cuboid.max[axis] += 1;
+            }
+        }
+
+        cuboid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, CellState, Family};
+    use std::io::Cursor;
+
+    fn check_covers(model: &Matrix, cuboids: &[Cuboid], max_side: usize) {
+        let mut union = Matrix::new(model.r);
+        for cuboid in cuboids {
+            assert!((0..3).all(|axis| cuboid.side(axis) <= max_side));
+            for [x, y, z] in cuboid.voxels() {
+                assert!(!union.is_filled(x, y, z), "{cuboid:?} overlaps");
+                union.set(x, y, z, CellState::Fill);
+            }
+        }
+        assert_eq!(*model, union);
+    }
+
+    #[test]
+    fn test_cuboids_cover_model() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA002_tgt.mdl");
+        let mut models = vec![crate::read_matrix(&mut Cursor::new(data))?];
+        for family in Family::ALL {
+            models.push(generate(family, 20, 5)?);
+        }
+
+        for model in &models {
+            check_covers(model, &model.cuboids(None), model.r);
+            check_covers(model, &model.cuboids(Some(4)), 4);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cuboids_of_solid_shapes() -> anyhow::Result<()> {
+        let model: Matrix = "
+            .....
+            .###.
+            .###.
+            .#...
+            .....
+
+            .....
+            .###.
+            .###.
+            .....
+            .....
+        "
+        .parse()?;
+
+        // The 3x2x2 block, then the voxel left under it.
+        let cuboids = model.cuboids(None);
+        assert_eq!(2, cuboids.len());
+        assert_eq!(12, cuboids[0].volume());
+        assert_eq!(3, model.cuboids(Some(2)).len());
+        Ok(())
+    }
+}
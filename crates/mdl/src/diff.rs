@@ -0,0 +1,155 @@
+use crate::transform::grow_bounds;
+use crate::Matrix;
+use std::fmt;
+
+/// Coordinates kept for each kind of mismatch.
+const FIRST_VOXELS: usize = 10;
+
+/// Voxels Full on one side of a diff only.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mismatch {
+    pub count: usize,
+    /// Smallest and largest coordinates of the voxels.
+    pub bounds: Option<([usize; 3], [usize; 3])>,
+    /// First voxels in index order, at most `FIRST_VOXELS` of them.
+    pub first: Vec<[usize; 3]>,
+}
+
+impl Mismatch {
+    fn add(&mut self, position: [usize; 3]) {
+        self.count += 1;
+        self.bounds = grow_bounds(self.bounds, position);
+        if self.first.len() < FIRST_VOXELS {
+            self.first.push(position);
+        }
+    }
+}
+
+/// Difference between a model and the one it's expected to be.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    /// Full in the expected model only.
+    pub missing: Mismatch,
+    /// Full in the actual model only.
+    pub extra: Mismatch,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.count == 0 && self.extra.count == 0
+    }
+}
+
+/// Sums up mismatches given in index order, as `Matrix::mismatches` yields
+/// them.
+impl FromIterator<([usize; 3], MismatchKind)> for Diff {
+    fn from_iter<I: IntoIterator<Item = ([usize; 3], MismatchKind)>>(mismatches: I) -> Self {
+        let mut diff = Diff::default();
+        for (position, kind) in mismatches {
+            match kind {
+                MismatchKind::Missing => diff.missing.add(position),
+                MismatchKind::Extra => diff.extra.add(position),
+            }
+        }
+        diff
+    }
+}
+
+/// Side of a diff a voxel is Full on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchKind {
+    Missing,
+    Extra,
+}
+
+impl Matrix {
+    /// Every voxel Full in only one of this model and `expected`, in index
+    /// order. Voxels outside the smaller of two resolutions count as Void.
+    pub fn mismatches<'a>(
+        &'a self,
+        expected: &'a Matrix,
+    ) -> impl Iterator<Item = ([usize; 3], MismatchKind)> + 'a {
+        let r = self.r.max(expected.r);
+        let filled = |matrix: &Matrix, x: usize, y: usize, z: usize| {
+            x < matrix.r && y < matrix.r && z < matrix.r && matrix.is_filled(x, y, z)
+        };
+
+        (0..r)
+            .flat_map(move |x| (0..r).flat_map(move |y| (0..r).map(move |z| [x, y, z])))
+            .filter_map(
+                move |[x, y, z]| match (filled(self, x, y, z), filled(expected, x, y, z)) {
+                    (false, true) => Some(([x, y, z], MismatchKind::Missing)),
+                    (true, false) => Some(([x, y, z], MismatchKind::Extra)),
+                    _ => None,
+                },
+            )
+    }
+
+    /// Compares this model with `expected`, summing up `mismatches`.
+    pub fn diff(&self, expected: &Matrix) -> Diff {
+        self.mismatches(expected).collect()
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.count)?;
+        if let Some((min, max)) = self.bounds {
+            write!(f, " in {min:?}..={max:?}, first {:?}", self.first)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "missing {}, extra {}", self.missing, self.extra)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() -> anyhow::Result<()> {
+        let expected: Matrix = "
+            ...
+            .##
+            ...
+        "
+        .parse()?;
+        let actual: Matrix = "
+            ...
+            .#.
+            ...
+
+            ...
+            .#.
+            ...
+        "
+        .parse()?;
+
+        let diff = actual.diff(&expected);
+
+        assert!(!diff.is_empty());
+        assert_eq!(vec![[1, 0, 2]], diff.missing.first);
+        assert_eq!(vec![[1, 1, 1]], diff.extra.first);
+        assert_eq!(Some(([1, 1, 1], [1, 1, 1])), diff.extra.bounds);
+        assert_eq!(
+            "missing 1 in [1, 0, 2]..=[1, 0, 2], first [[1, 0, 2]], extra 1 in \
+             [1, 1, 1]..=[1, 1, 1], first [[1, 1, 1]]",
+            diff.to_string()
+        );
+        assert_eq!(
+            vec![
+                ([1, 0, 2], MismatchKind::Missing),
+                ([1, 1, 1], MismatchKind::Extra)
+            ],
+            actual.mismatches(&expected).collect::<Vec<_>>()
+        );
+        assert!(expected.diff(&expected).is_empty());
+        assert_eq!(2, Matrix::new(5).diff(&expected).missing.count);
+        Ok(())
+    }
+}
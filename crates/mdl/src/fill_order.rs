@@ -0,0 +1,85 @@
+use crate::{CellState, Matrix, NEIGHBOURS};
+use std::collections::VecDeque;
+
+/// Order in which the Full voxels of a model can be filled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FillOrder {
+    /// Voxels connected to the floor, each one touching the floor or a voxel
+    /// listed before it, so filling them in order keeps the model grounded.
+    pub grounded: Vec<(usize, usize, usize)>,
+    /// Voxels with no face-adjacent path to the floor, lowest first. They
+    /// can only be filled while harmonics are High.
+    pub floating: Vec<(usize, usize, usize)>,
+}
+
+impl Matrix {
+    /// Plans a fill order with a breadth-first search from the y=0 floor
+    /// through face-adjacent Full voxels.
+    pub fn fill_order(&self) -> FillOrder {
+        let r = self.r;
+        let mut visited = vec![false; r * r * r];
+        let mut queue = self
+            .cells
+            .iter()
+            .filter(|cell| cell.y == 0 && cell.state == CellState::Fill)
+            .map(|cell| cell.index)
+            .collect::<VecDeque<_>>();
+        for index in &queue {
+            visited[*index] = true;
+        }
+
+        let mut order = FillOrder::default();
+        while let Some(index) = queue.pop_front() {
+            let cell = &self.cells[index];
+            order.grounded.push((cell.x, cell.y, cell.z));
+
+            let (x, y, z) = (cell.x as i64, cell.y as i64, cell.z as i64);
+            for (dx, dy, dz) in NEIGHBOURS {
+                let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+                if [nx, ny, nz].iter().any(|v| *v < 0 || *v >= r as i64) {
+                    continue;
+                }
+
+                let next = nx as usize * r * r + ny as usize * r + nz as usize;
+                if !visited[next] && self.cells[next].state == CellState::Fill {
+                    visited[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        order.floating = self
+            .cells
+            .iter()
+            .filter(|cell| cell.state == CellState::Fill && !visited[cell.index])
+            .map(|cell| (cell.x, cell.y, cell.z))
+            .collect();
+        order.floating.sort_by_key(|(x, y, z)| (*y, *x, *z));
+
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_order_keeps_model_grounded() {
+        let mut model = Matrix::new(6);
+        for (x, y, z) in [(2, 0, 2), (2, 1, 2), (2, 2, 2), (3, 2, 2), (4, 2, 2)] {
+            model.set(x, y, z, CellState::Fill);
+        }
+        model.set(1, 4, 1, CellState::Fill);
+
+        let order = model.fill_order();
+        assert_eq!(vec![(1, 4, 1)], order.floating);
+        assert_eq!(5, order.grounded.len());
+
+        let mut placed = Matrix::new(6);
+        for (x, y, z) in order.grounded {
+            placed.set(x, y, z, CellState::Fill);
+            assert!(placed.is_grounded());
+        }
+    }
+}
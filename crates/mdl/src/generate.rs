@@ -0,0 +1,264 @@
+use crate::{CellState, Matrix};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::str::FromStr;
+
+/// Shapes `generate` builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    /// Solid cuboid on the floor.
+    Box,
+    /// Cuboid with walls one voxel thick.
+    Shell,
+    /// Stepped pyramid, each layer one voxel smaller on every side.
+    Pyramid,
+    /// Row of arches, lintels overhanging the space between two pillars.
+    Arches,
+    /// Trunks branching out sideways and up at random.
+    Trees,
+    /// Columns of random footprints and heights.
+    Towers,
+    /// Menger sponge.
+    Sponge,
+}
+
+impl Family {
+    pub const ALL: [Family; 7] = [
+        Family::Box,
+        Family::Shell,
+        Family::Pyramid,
+        Family::Arches,
+        Family::Trees,
+        Family::Towers,
+        Family::Sponge,
+    ];
+}
+
+impl FromStr for Family {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Family::ALL
+            .into_iter()
+            .find(|family| format!("{family:?}").eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow::anyhow!("Unknown model family {name:?}"))
+    }
+}
+
+/// Builds a grounded model of resolution `r` from `family`, keeping clear
+/// of the sides bots need to go around it. The same seed gives the same
+/// model.
+pub fn generate(family: Family, r: usize, seed: u64) -> anyhow::Result<Matrix> {
+    if r < 5 {
+        return Err(anyhow::anyhow!("Resolution {r} is too small to generate"));
+    }
+
+    let mut canvas = Canvas {
+        matrix: Matrix::new(r),
+        rng: StdRng::seed_from_u64(seed),
+        // Full voxels live in 1..=r-2 along x and z, and 0..=r-2 along y.
+        size: r - 2,
+    };
+    match family {
+        Family::Box => canvas.solid_box(),
+        Family::Shell => canvas.shell(),
+        Family::Pyramid => canvas.pyramid(),
+        Family::Arches => canvas.arches(),
+        Family::Trees => canvas.trees(),
+        Family::Towers => canvas.towers(),
+        Family::Sponge => canvas.sponge(),
+    }
+    Ok(canvas.matrix)
+}
+
+struct Canvas {
+    matrix: Matrix,
+    rng: StdRng,
+    size: usize,
+}
+
+impl Canvas {
+    /// Fills a box of the usable space, `x` and `z` counted from its side.
+    fn fill(&mut self, x: (usize, usize), y: (usize, usize), z: (usize, usize)) {
+        for x in x.0..x.1 {
+            for y in y.0..y.1 {
+                for z in z.0..z.1 {
+                    self.matrix.set(x + 1, y, z + 1, CellState::Fill);
+                }
+            }
+        }
+    }
+
+    /// Random range of at least `min` voxels out of `size`.
+    fn span(&mut self, min: usize) -> (usize, usize) {
+        let length = self.rng.gen_range(min.min(self.size)..=self.size);
+        let start = self.rng.gen_range(0..=self.size - length);
+        (start, start + length)
+    }
+
+    fn solid_box(&mut self) {
+        let half = self.size.div_ceil(2);
+        let (x, y, z) = (self.span(half), self.span(half), self.span(half));
+        self.fill(x, (0, y.1 - y.0), z);
+    }
+
+    fn shell(&mut self) {
+        let (x, y, z) = (self.span(3), self.span(3), self.span(3));
+        let height = y.1 - y.0;
+        self.fill(x, (0, height), z);
+        if x.1 - x.0 > 2 && height > 2 && z.1 - z.0 > 2 {
+            self.clear((x.0 + 1, x.1 - 1), (1, height - 1), (z.0 + 1, z.1 - 1));
+        }
+    }
+
+    fn clear(&mut self, x: (usize, usize), y: (usize, usize), z: (usize, usize)) {
+        for x in x.0..x.1 {
+            for y in y.0..y.1 {
+                for z in z.0..z.1 {
+                    self.matrix.set(x + 1, y, z + 1, CellState::Void);
+                }
+            }
+        }
+    }
+
+    fn pyramid(&mut self) {
+        let (mut x, mut z) = (self.span(1), self.span(1));
+        let mut y = 0;
+        while x.0 < x.1 && z.0 < z.1 && y < self.size + 1 {
+            self.fill(x, (y, y + 1), z);
+            (x, z, y) = ((x.0 + 1, x.1 - 1), (z.0 + 1, z.1 - 1), y + 1);
+            if x.1 < x.0 + 1 || z.1 < z.0 + 1 {
+                break;
+            }
+        }
+    }
+
+    fn arches(&mut self) {
+        let depth = self.span(1);
+        let mut x = 0;
+        while x + 3 <= self.size {
+            let width = self.rng.gen_range(3..=(self.size - x).min(8));
+            let height = self.rng.gen_range(2..=self.size + 1);
+            // Pillars, then a lintel on top of them spanning the gap.
+            self.fill((x, x + 1), (0, height - 1), depth);
+            self.fill((x + width - 1, x + width), (0, height - 1), depth);
+            self.fill((x, x + width), (height - 1, height), depth);
+            x += width + self.rng.gen_range(0..=2);
+        }
+    }
+
+    fn trees(&mut self) {
+        let count = self.rng.gen_range(1..=self.size.div_ceil(4));
+        for _ in 0..count {
+            let mut tips = vec![[
+                self.rng.gen_range(0..self.size),
+                0,
+                self.rng.gen_range(0..self.size),
+            ]];
+            let mut grown = 0;
+            while let Some(tip) = tips.pop() {
+                self.fill(
+                    (tip[0], tip[0] + 1),
+                    (tip[1], tip[1] + 1),
+                    (tip[2], tip[2] + 1),
+                );
+                grown += 1;
+                if grown > 4 * self.size {
+                    break;
+                }
+
+                // Mostly grow up, sometimes fork sideways.
+                if tip[1] < self.size && self.rng.gen_bool(0.85) {
+                    tips.push([tip[0], tip[1] + 1, tip[2]]);
+                }
+                if self.rng.gen_bool(0.25) {
+                    let (axis, step) = (self.rng.gen_range(0..2) * 2, self.rng.gen_bool(0.5));
+                    let mut branch = tip;
+                    match step {
+                        true if branch[axis] + 1 < self.size => branch[axis] += 1,
+                        false if branch[axis] > 0 => branch[axis] -= 1,
+                        _ => continue,
+                    }
+                    tips.push(branch);
+                }
+            }
+        }
+    }
+
+    fn towers(&mut self) {
+        let count = self.rng.gen_range(1..=self.size.div_ceil(2));
+        for _ in 0..count {
+            let footprint = self.size.div_ceil(4);
+            let x = self.rng.gen_range(0..self.size);
+            let z = self.rng.gen_range(0..self.size);
+            let width = self.rng.gen_range(1..=footprint).min(self.size - x);
+            let depth = self.rng.gen_range(1..=footprint).min(self.size - z);
+            let height = self.rng.gen_range(1..=self.size + 1);
+            self.fill((x, x + width), (0, height), (z, z + depth));
+        }
+    }
+
+    fn sponge(&mut self) {
+        let mut side = 1;
+        while side * 3 <= self.size {
+            side *= 3;
+        }
+        let x = self.rng.gen_range(0..=self.size - side);
+        let z = self.rng.gen_range(0..=self.size - side);
+
+        for dx in 0..side {
+            for dy in 0..side {
+                for dz in 0..side {
+                    if in_sponge(dx, dy, dz) {
+                        self.fill((x + dx, x + dx + 1), (dy, dy + 1), (z + dz, z + dz + 1));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether a voxel is left in a Menger sponge: no two of its coordinates
+/// sit in the middle third at any scale.
+fn in_sponge(mut x: usize, mut y: usize, mut z: usize) -> bool {
+    while x > 0 || y > 0 || z > 0 {
+        if [x % 3, y % 3, z % 3]
+            .iter()
+            .filter(|digit| **digit == 1)
+            .count()
+            >= 2
+        {
+            return false;
+        }
+        (x, y, z) = (x / 3, y / 3, z / 3);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_models_are_valid() -> anyhow::Result<()> {
+        for family in Family::ALL {
+            for (r, seed) in [(5, 0), (10, 1), (20, 2), (30, 3)] {
+                let matrix = generate(family, r, seed)?;
+                let filled = matrix
+                    .iter()
+                    .filter(|cell| cell.state == CellState::Fill)
+                    .collect::<Vec<_>>();
+
+                assert!(!filled.is_empty(), "{family:?} r={r} is empty");
+                assert!(matrix.is_grounded(), "{family:?} r={r} isn't grounded");
+                for cell in filled {
+                    assert!((1..r - 1).contains(&cell.x) && (1..r - 1).contains(&cell.z));
+                    assert!(cell.y < r - 1);
+                }
+                assert_eq!(matrix, generate(family, r, seed)?);
+            }
+        }
+        assert_eq!(Family::Sponge, "sponge".parse()?);
+        Ok(())
+    }
+}
@@ -1,6 +1,24 @@
-use std::io::BufRead;
-
-#[derive(Debug, Clone, Eq, PartialEq)]
+mod ascii;
+mod cuboids;
+mod diff;
+mod fill_order;
+mod generate;
+mod report;
+mod sparse;
+mod transform;
+mod vox;
+
+pub use cuboids::Cuboid;
+pub use diff::{Diff, Mismatch, MismatchKind};
+pub use fill_order::FillOrder;
+pub use generate::{generate, Family};
+pub use report::Report;
+pub use sparse::SparseMatrix;
+use std::io::{BufRead, Write};
+pub use transform::Symmetry;
+pub use vox::{read_vox, write_vox};
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum CellState {
     Fill,
     Void,
@@ -15,6 +33,16 @@ pub struct Cell {
     pub state: CellState,
 }
 
+/// Face-adjacent offsets of a voxel.
+pub const NEIGHBOURS: [(i64, i64, i64); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Matrix {
     cells: Vec<Cell>,
@@ -48,6 +76,29 @@ impl Matrix {
         self.cells[index].state = state;
     }
 
+    pub fn get(&self, x: usize, y: usize, z: usize) -> &CellState {
+        let index = x * self.r * self.r + y * self.r + z;
+        &self.cells[index].state
+    }
+
+    pub fn is_filled(&self, x: usize, y: usize, z: usize) -> bool {
+        *self.get(x, y, z) == CellState::Fill
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cell> {
+        self.cells.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(|cell| cell.state == CellState::Void)
+    }
+
+    /// Checks that every Full voxel is connected to the y=0 floor through
+    /// face-adjacent Full voxels.
+    pub fn is_grounded(&self) -> bool {
+        self.fill_order().floating.is_empty()
+    }
+
     pub fn get_level(&self, y: usize) -> Vec<Cell> {
         let r = self.r;
         let mut result = Vec::with_capacity(r * r);
@@ -70,13 +121,6 @@ fn get_state(byte: u8, position: usize) -> CellState {
     }
 }
 
-fn read_matrix_cells(reader: impl BufRead) -> impl Iterator<Item = CellState> {
-    reader
-        .bytes()
-        .filter_map(Result::ok)
-        .flat_map(|byte| (0..8).map(move |position| get_state(byte, position)))
-}
-
 pub fn read_matrix(reader: &mut impl BufRead) -> anyhow::Result<Matrix> {
     let mut r_bytes = [0u8; 1];
     let r_bytes_count = reader.read(&mut r_bytes)?;
@@ -84,51 +128,97 @@ pub fn read_matrix(reader: &mut impl BufRead) -> anyhow::Result<Matrix> {
         return Err(anyhow::anyhow!("Not enough bytes for resolution"));
     }
 
-    let mut cells_iterator = read_matrix_cells(reader);
-
     let r = r_bytes[0] as usize;
     let expected_cell_count = r * r * r;
-    let mut cell_processed = 0;
-
-    let mut cells = vec![];
-
-    while let Some(state) = cells_iterator.next() {
-        if cell_processed == expected_cell_count {
-            return Err(anyhow::anyhow!(
-                "Too many cells in model, expected {expected_cell_count}, r={r}"
-            ));
-        }
-
-        let x = cell_processed / (r * r); // Near to far
-        let y = (cell_processed / r) % r; // Bottom to top
-        let z = cell_processed % r; // Left to right
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
 
-        cells.push(Cell {
-            index: cell_processed,
-            state,
-            x,
-            y,
-            z,
-        });
-
-        cell_processed += 1;
+    // Cells are packed 8 per byte, the last byte is padded when R³ isn't a
+    // multiple of 8.
+    let expected_bytes = expected_cell_count.div_ceil(8);
+    if bytes.len() > expected_bytes {
+        return Err(anyhow::anyhow!(
+            "Too many cells in model, expected {expected_cell_count}, r={r}"
+        ));
     }
-
-    if cell_processed < expected_cell_count {
+    if bytes.len() < expected_bytes {
         return Err(anyhow::anyhow!(
-            "Too few cells in model, expected {expected_cell_count}, got {cell_processed}"
+            "Too few cells in model, expected {expected_cell_count}, got {}",
+            bytes.len() * 8
         ));
     }
 
+    let cells = bytes
+        .iter()
+        .flat_map(|byte| (0..8).map(move |position| get_state(*byte, position)))
+        .take(expected_cell_count)
+        .enumerate()
+        .map(|(index, state)| Cell {
+            index,
+            state,
+            x: index / (r * r), // Near to far
+            y: (index / r) % r, // Bottom to top
+            z: index % r,       // Left to right
+        })
+        .collect();
+
     Ok(Matrix { cells, r })
 }
 
+/// Writes the resolution then the cells 8 per byte, in the layout
+/// `read_matrix` expects.
+pub fn write_matrix(writer: &mut impl Write, matrix: &Matrix) -> anyhow::Result<()> {
+    let r = u8::try_from(matrix.r)
+        .map_err(|_| anyhow::anyhow!("Resolution {} doesn't fit a byte", matrix.r))?;
+    writer.write_all(&[r])?;
+
+    let bytes = matrix
+        .cells
+        .chunks(8)
+        .map(|cells| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.state == CellState::Fill)
+                .fold(0u8, |byte, (position, _)| byte | 1 << position)
+        })
+        .collect::<Vec<_>>();
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rand::prelude::*;
     use std::io::Cursor;
 
+    #[test]
+    fn test_model_with_padding() -> anyhow::Result<()> {
+        // 27 cells take 4 bytes, the 5 last bits are padding.
+        let data = [3, 0b0000_0001, 0, 0, 0b0000_0100];
+        let matrix = read_matrix(&mut Cursor::new(data))?;
+
+        assert_eq!(27, matrix.cells.len());
+        assert!(matrix.is_filled(0, 0, 0));
+        assert!(matrix.is_filled(2, 2, 2));
+        assert!(read_matrix(&mut Cursor::new([3, 0, 0, 0, 0, 0])).is_err());
+        assert!(read_matrix(&mut Cursor::new([3, 0, 0, 0])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_matrix_round_trip() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA004_tgt.mdl");
+        let model = read_matrix(&mut Cursor::new(data))?;
+
+        let mut written = vec![];
+        write_matrix(&mut written, &model)?;
+
+        assert_eq!(data.to_vec(), written);
+        Ok(())
+    }
+
     #[test]
     fn test_model_loaded() -> anyhow::Result<()> {
         let data = include_bytes!("../../../data/FA004_tgt.mdl");
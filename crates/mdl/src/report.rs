@@ -0,0 +1,175 @@
+use crate::cuboids::Cuboid;
+use crate::{CellState, Matrix, NEIGHBOURS};
+use std::fmt;
+
+/// Cuboids listed in a report.
+const LARGEST_CUBOIDS: usize = 5;
+
+/// Structure of a model, see `Matrix::report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub r: usize,
+    pub filled: usize,
+    /// Smallest and largest coordinates of the Full voxels.
+    pub bounds: Option<([usize; 3], [usize; 3])>,
+    /// Full voxels of every y layer, from the floor up.
+    pub layers: Vec<usize>,
+    /// Groups of face-connected Full voxels.
+    pub components: usize,
+    /// Full voxels above a Void one.
+    pub overhangs: usize,
+    /// Largest cuboids of a decomposition of the Full voxels, largest first.
+    pub largest_cuboids: Vec<Cuboid>,
+    pub grounded: bool,
+}
+
+impl Matrix {
+    pub fn report(&self) -> Report {
+        let r = self.r;
+        let mut layers = vec![0; r];
+        let mut overhangs = 0;
+        for cell in self.iter().filter(|cell| cell.state == CellState::Fill) {
+            layers[cell.y] += 1;
+            if cell.y > 0 && !self.is_filled(cell.x, cell.y - 1, cell.z) {
+                overhangs += 1;
+            }
+        }
+
+        let mut largest_cuboids = self.cuboids(None);
+        largest_cuboids.sort_by_key(|cuboid| std::cmp::Reverse(cuboid.volume()));
+        largest_cuboids.truncate(LARGEST_CUBOIDS);
+
+        Report {
+            r,
+            filled: layers.iter().sum(),
+            bounds: self.bounds(),
+            layers,
+            components: self.components(),
+            overhangs,
+            largest_cuboids,
+            grounded: self.is_grounded(),
+        }
+    }
+
+    /// Counts groups of face-connected Full voxels with a flood fill.
+    fn components(&self) -> usize {
+        let r = self.r;
+        let mut visited = vec![false; r * r * r];
+        let mut components = 0;
+
+        for start in self.iter().filter(|cell| cell.state == CellState::Fill) {
+            if visited[start.index] {
+                continue;
+            }
+            components += 1;
+            visited[start.index] = true;
+
+            let mut stack = vec![(start.x, start.y, start.z)];
+            while let Some((x, y, z)) = stack.pop() {
+                for (dx, dy, dz) in NEIGHBOURS {
+                    let (nx, ny, nz) = (x as i64 + dx, y as i64 + dy, z as i64 + dz);
+                    if [nx, ny, nz].iter().any(|v| *v < 0 || *v >= r as i64) {
+                        continue;
+                    }
+                    let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
+                    let index = nx * r * r + ny * r + nz;
+                    if !visited[index] && self.is_filled(nx, ny, nz) {
+                        visited[index] = true;
+                        stack.push((nx, ny, nz));
+                    }
+                }
+            }
+        }
+
+        components
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let volume = self.r.pow(3).max(1);
+        writeln!(f, "resolution {}", self.r)?;
+        writeln!(
+            f,
+            "filled {} ({:.2}%)",
+            self.filled,
+            100.0 * self.filled as f64 / volume as f64
+        )?;
+        match self.bounds {
+            Some((min, max)) => writeln!(f, "bounds {min:?}..={max:?}")?,
+            None => writeln!(f, "bounds none")?,
+        }
+        writeln!(f, "grounded {}", self.grounded)?;
+        writeln!(f, "components {}", self.components)?;
+        writeln!(f, "overhangs {}", self.overhangs)?;
+
+        writeln!(f, "layers")?;
+        for (y, count) in self
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+        {
+            writeln!(f, "  y={y} {count}")?;
+        }
+        writeln!(f, "largest cuboids")?;
+        for cuboid in &self.largest_cuboids {
+            writeln!(
+                f,
+                "  {:?}..={:?} {}",
+                cuboid.min,
+                cuboid.max,
+                cuboid.volume()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() -> anyhow::Result<()> {
+        let model: Matrix = "
+            .....
+            .##..
+            .##..
+            .....
+            ...#.
+
+            .....
+            .#...
+            .....
+            .....
+            .....
+
+            .....
+            .###.
+            .....
+            .....
+            .....
+
+            .....
+            .....
+            .....
+            ...#.
+            .....
+        "
+        .parse()?;
+
+        let report = model.report();
+
+        assert_eq!(10, report.filled);
+        assert_eq!(Some(([1, 0, 1], [4, 3, 3])), report.bounds);
+        assert_eq!(vec![5, 1, 3, 1, 0], report.layers);
+        assert_eq!(3, report.components);
+        // The two voxels cantilevered on layer 2 and the floating one.
+        assert_eq!(3, report.overhangs);
+        assert!(!report.grounded);
+        assert_eq!(4, report.largest_cuboids[0].volume());
+        assert!(report.to_string().contains("components 3\n"));
+        Ok(())
+    }
+}
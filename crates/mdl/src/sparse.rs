@@ -0,0 +1,280 @@
+use crate::{CellState, Cuboid, Matrix};
+
+/// Side of a chunk, 8³ voxels fit 8 words of 64 bits.
+const CHUNK: usize = 8;
+
+/// Full voxels of a model stored as 8³ bitsets, only for chunks holding
+/// some, so queries over mostly empty models skip whole chunks at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseMatrix {
+    r: usize,
+    /// Chunks per side.
+    side: usize,
+    /// One word per local x, bit `y * 8 + z`.
+    chunks: Vec<Option<Box<[u64; CHUNK]>>>,
+    filled: usize,
+}
+
+impl SparseMatrix {
+    pub fn new(r: usize) -> Self {
+        let side = r.div_ceil(CHUNK);
+        Self {
+            r,
+            side,
+            chunks: vec![None; side * side * side],
+            filled: 0,
+        }
+    }
+
+    pub fn r(&self) -> usize {
+        self.r
+    }
+
+    /// Number of Full voxels.
+    pub fn len(&self) -> usize {
+        self.filled
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filled == 0
+    }
+
+    pub fn is_filled(&self, x: usize, y: usize, z: usize) -> bool {
+        let (chunk, word, bit) = self.locate([x, y, z]);
+        self.chunks[chunk]
+            .as_ref()
+            .is_some_and(|words| words[word] & bit != 0)
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, state: CellState) {
+        let (chunk, word, bit) = self.locate([x, y, z]);
+        match state {
+            CellState::Fill => {
+                let words = self.chunks[chunk].get_or_insert_with(|| Box::new([0; CHUNK]));
+                if words[word] & bit == 0 {
+                    words[word] |= bit;
+                    self.filled += 1;
+                }
+            }
+            CellState::Void => {
+                let Some(words) = &mut self.chunks[chunk] else {
+                    return;
+                };
+                if words[word] & bit != 0 {
+                    words[word] &= !bit;
+                    self.filled -= 1;
+                    if words.iter().all(|word| *word == 0) {
+                        self.chunks[chunk] = None;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether no voxel of `cuboid` is Full.
+    pub fn is_box_empty(&self, cuboid: &Cuboid) -> bool {
+        self.chunks_in(cuboid).all(|(origin, words)| {
+            let (low, high) = local_range(cuboid, origin);
+            let mask = plane_mask(low, high);
+            (low[0]..=high[0]).all(|x| words[x] & mask == 0)
+        })
+    }
+
+    /// Full voxels of `cuboid`, chunk by chunk.
+    pub fn filled_in<'a>(&'a self, cuboid: &'a Cuboid) -> impl Iterator<Item = [usize; 3]> + 'a {
+        self.chunks_in(cuboid).flat_map(move |(origin, words)| {
+            let (low, high) = local_range(cuboid, origin);
+            let mask = plane_mask(low, high);
+            (low[0]..=high[0]).flat_map(move |x| {
+                bits(words[x] & mask).map(move |bit| {
+                    [
+                        origin[0] + x,
+                        origin[1] + bit / CHUNK,
+                        origin[2] + bit % CHUNK,
+                    ]
+                })
+            })
+        })
+    }
+
+    /// Every Full voxel, chunk by chunk.
+    pub fn iter(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, words)| Some((self.origin(index), words.as_ref()?)))
+            .flat_map(|(origin, words)| {
+                words.iter().enumerate().flat_map(move |(x, word)| {
+                    bits(*word).map(move |bit| {
+                        [
+                            origin[0] + x,
+                            origin[1] + bit / CHUNK,
+                            origin[2] + bit % CHUNK,
+                        ]
+                    })
+                })
+            })
+    }
+
+    /// First Full voxel after `from` along `axis`, towards larger
+    /// coordinates when `forward`. Empty chunks are skipped whole.
+    pub fn next_filled(&self, from: [usize; 3], axis: usize, forward: bool) -> Option<[usize; 3]> {
+        let mut position = from;
+        loop {
+            position[axis] = match forward {
+                true if position[axis] + 1 < self.r => position[axis] + 1,
+                false if position[axis] > 0 => position[axis] - 1,
+                _ => return None,
+            };
+
+            let (chunk, _, _) = self.locate(position);
+            if self.chunks[chunk].is_none() {
+                // Jump to the last voxel of the chunk in the direction of
+                // travel, the next step leaves it.
+                let start = position[axis] / CHUNK * CHUNK;
+                position[axis] = match forward {
+                    true => (start + CHUNK - 1).min(self.r - 1),
+                    false => start,
+                };
+                continue;
+            }
+            if self.is_filled(position[0], position[1], position[2]) {
+                return Some(position);
+            }
+        }
+    }
+
+    /// Chunk index, word and bit of a voxel.
+    fn locate(&self, [x, y, z]: [usize; 3]) -> (usize, usize, u64) {
+        let chunk = ((x / CHUNK) * self.side + y / CHUNK) * self.side + z / CHUNK;
+        let bit = 1 << ((y % CHUNK) * CHUNK + z % CHUNK);
+        (chunk, x % CHUNK, bit)
+    }
+
+    fn origin(&self, index: usize) -> [usize; 3] {
+        let side = self.side;
+        [index / (side * side), (index / side) % side, index % side].map(|chunk| chunk * CHUNK)
+    }
+
+    /// Non-empty chunks overlapping `cuboid`, with their origin.
+    fn chunks_in<'a>(
+        &'a self,
+        cuboid: &'a Cuboid,
+    ) -> impl Iterator<Item = ([usize; 3], &'a [u64; CHUNK])> + 'a {
+        let range = move |axis: usize| {
+            cuboid.min[axis] / CHUNK..=cuboid.max[axis].min(self.r.saturating_sub(1)) / CHUNK
+        };
+        range(0).flat_map(move |cx| {
+            range(1).flat_map(move |cy| {
+                range(2).filter_map(move |cz| {
+                    let index = (cx * self.side + cy) * self.side + cz;
+                    let words = self.chunks.get(index)?.as_deref()?;
+                    Some(([cx, cy, cz].map(|chunk| chunk * CHUNK), words))
+                })
+            })
+        })
+    }
+}
+
+/// Local coordinates of the part of `cuboid` inside the chunk at `origin`.
+fn local_range(cuboid: &Cuboid, origin: [usize; 3]) -> ([usize; 3], [usize; 3]) {
+    let low = [0, 1, 2].map(|axis| cuboid.min[axis].max(origin[axis]) - origin[axis]);
+    let high = [0, 1, 2].map(|axis| (cuboid.max[axis] - origin[axis]).min(CHUNK - 1));
+    (low, high)
+}
+
+/// Bits of a word covering local y and z within the bounds.
+fn plane_mask(low: [usize; 3], high: [usize; 3]) -> u64 {
+    let row = ((1u64 << (high[2] + 1)) - 1) & !((1u64 << low[2]) - 1);
+    (low[1]..=high[1]).fold(0, |mask, y| mask | row << (y * CHUNK))
+}
+
+/// Indices of the set bits of a word, lowest first.
+fn bits(mut word: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let bit = (word != 0).then(|| word.trailing_zeros() as usize)?;
+        word &= word - 1;
+        Some(bit)
+    })
+}
+
+impl From<&Matrix> for SparseMatrix {
+    fn from(matrix: &Matrix) -> Self {
+        let mut sparse = SparseMatrix::new(matrix.r);
+        for cell in matrix.iter().filter(|cell| cell.state == CellState::Fill) {
+            sparse.set(cell.x, cell.y, cell.z, CellState::Fill);
+        }
+        sparse
+    }
+}
+
+impl From<&SparseMatrix> for Matrix {
+    fn from(sparse: &SparseMatrix) -> Self {
+        let mut matrix = Matrix::new(sparse.r);
+        for [x, y, z] in sparse.iter() {
+            matrix.set(x, y, z, CellState::Fill);
+        }
+        matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_sparse_matches_matrix() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let r = 21;
+        let mut matrix = Matrix::new(r);
+        for _ in 0..150 {
+            let [x, y, z] = [0; 3].map(|_| rng.gen_range(0..r));
+            matrix.set(x, y, z, CellState::Fill);
+        }
+
+        let mut sparse = SparseMatrix::from(&matrix);
+        assert_eq!(matrix, Matrix::from(&sparse));
+        assert_eq!(sparse.iter().count(), sparse.len());
+
+        for _ in 0..200 {
+            let a = [0; 3].map(|_| rng.gen_range(0..r));
+            let b = [0; 3].map(|_| rng.gen_range(0..r));
+            let cuboid = Cuboid {
+                min: [0, 1, 2].map(|axis| a[axis].min(b[axis])),
+                max: [0, 1, 2].map(|axis| a[axis].max(b[axis])),
+            };
+            let expected = cuboid
+                .voxels()
+                .filter(|[x, y, z]| matrix.is_filled(*x, *y, *z))
+                .collect::<Vec<_>>();
+            let mut filled = sparse.filled_in(&cuboid).collect::<Vec<_>>();
+            filled.sort();
+            assert_eq!(expected, filled);
+            assert_eq!(expected.is_empty(), sparse.is_box_empty(&cuboid));
+
+            let axis = rng.gen_range(0..3);
+            for forward in [true, false] {
+                let mut next = a;
+                let expected = loop {
+                    next[axis] = match forward {
+                        true if next[axis] + 1 < r => next[axis] + 1,
+                        false if next[axis] > 0 => next[axis] - 1,
+                        _ => break None,
+                    };
+                    if matrix.is_filled(next[0], next[1], next[2]) {
+                        break Some(next);
+                    }
+                };
+                assert_eq!(expected, sparse.next_filled(a, axis, forward));
+            }
+        }
+
+        for [x, y, z] in sparse.iter().collect::<Vec<_>>() {
+            sparse.set(x, y, z, CellState::Void);
+        }
+        assert!(sparse.is_empty());
+        assert!(sparse.chunks.iter().all(Option::is_none));
+    }
+}
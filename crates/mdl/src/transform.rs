@@ -0,0 +1,180 @@
+use crate::{CellState, Matrix};
+
+/// Symmetry of the space of a model keeping the y axis up, so grounded
+/// models stay grounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Quarter turns around the y axis, from x towards z.
+    RotateY(u8),
+    MirrorX,
+    MirrorZ,
+}
+
+impl Symmetry {
+    pub fn inverse(self) -> Self {
+        match self {
+            Symmetry::RotateY(turns) => Symmetry::RotateY((4 - turns % 4) % 4),
+            mirror => mirror,
+        }
+    }
+
+    /// Image of a position in a model of resolution `r`.
+    pub fn position(self, r: usize, [x, y, z]: [usize; 3]) -> [usize; 3] {
+        let far = r - 1;
+        match self {
+            Symmetry::RotateY(turns) => {
+                (0..turns % 4).fold([x, y, z], |[x, y, z], _| [z, y, far - x])
+            }
+            Symmetry::MirrorX => [far - x, y, z],
+            Symmetry::MirrorZ => [x, y, far - z],
+        }
+    }
+
+    /// Image of the difference between two positions.
+    pub fn difference(self, [dx, dy, dz]: [i8; 3]) -> [i8; 3] {
+        match self {
+            Symmetry::RotateY(turns) => {
+                (0..turns % 4).fold([dx, dy, dz], |[dx, dy, dz], _| [dz, dy, -dx])
+            }
+            Symmetry::MirrorX => [-dx, dy, dz],
+            Symmetry::MirrorZ => [dx, dy, -dz],
+        }
+    }
+}
+
+impl Matrix {
+    pub fn transformed(&self, symmetry: Symmetry) -> Matrix {
+        let mut matrix = Matrix::new(self.r);
+        for cell in self.filled() {
+            let [x, y, z] = symmetry.position(self.r, [cell.x, cell.y, cell.z]);
+            matrix.set(x, y, z, CellState::Fill);
+        }
+        matrix
+    }
+
+    /// Moves every Full voxel by `offset`, failing when one leaves the model.
+    pub fn translated(&self, offset: [i64; 3]) -> anyhow::Result<Matrix> {
+        let mut matrix = Matrix::new(self.r);
+        for cell in self.filled() {
+            let [x, y, z] = [cell.x, cell.y, cell.z];
+            let moved = [x, y, z]
+                .iter()
+                .zip(offset)
+                .map(|(value, d)| {
+                    usize::try_from(*value as i64 + d)
+                        .ok()
+                        .filter(|value| *value < self.r)
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Voxel {:?} moved by {offset:?} leaves the model", (x, y, z))
+                })?;
+            matrix.set(moved[0], moved[1], moved[2], CellState::Fill);
+        }
+        Ok(matrix)
+    }
+
+    /// Smallest and largest coordinates of the Full voxels, `None` when the
+    /// model is empty.
+    pub fn bounds(&self) -> Option<([usize; 3], [usize; 3])> {
+        self.filled()
+            .map(|cell| [cell.x, cell.y, cell.z])
+            .fold(None, grow_bounds)
+    }
+
+    /// Model only as large as the longest side of the bounding box, with
+    /// the box in its corner. An empty model crops to resolution 0.
+    pub fn cropped(&self) -> Matrix {
+        let Some((min, max)) = self.bounds() else {
+            return Matrix::new(0);
+        };
+        let r = (0..3)
+            .map(|axis| max[axis] - min[axis] + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut matrix = Matrix::new(r);
+        for cell in self.filled() {
+            matrix.set(
+                cell.x - min[0],
+                cell.y - min[1],
+                cell.z - min[2],
+                CellState::Fill,
+            );
+        }
+        matrix
+    }
+
+    /// Copies the model into one of resolution `r`, its origin at `offset`,
+    /// failing when a Full voxel doesn't fit.
+    pub fn embedded(&self, r: usize, offset: [usize; 3]) -> anyhow::Result<Matrix> {
+        let mut matrix = Matrix::new(r);
+        for cell in self.filled() {
+            let [x, y, z] = [cell.x + offset[0], cell.y + offset[1], cell.z + offset[2]];
+            if x >= r || y >= r || z >= r {
+                return Err(anyhow::anyhow!(
+                    "Voxel {:?} doesn't fit a model of resolution {r}",
+                    (x, y, z)
+                ));
+            }
+            matrix.set(x, y, z, CellState::Fill);
+        }
+        Ok(matrix)
+    }
+
+    fn filled(&self) -> impl Iterator<Item = &crate::Cell> {
+        self.iter().filter(|cell| cell.state == CellState::Fill)
+    }
+}
+
+/// `bounds` grown to hold `position`, the fold step of `Matrix::bounds`.
+pub(crate) fn grow_bounds(
+    bounds: Option<([usize; 3], [usize; 3])>,
+    position: [usize; 3],
+) -> Option<([usize; 3], [usize; 3])> {
+    let (min, max) = bounds.unwrap_or((position, position));
+    Some((
+        [0, 1, 2].map(|axis| min[axis].min(position[axis])),
+        [0, 1, 2].map(|axis| max[axis].max(position[axis])),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transforms() -> anyhow::Result<()> {
+        let model: Matrix = "
+            ....
+            .##.
+            .#..
+            ....
+
+            ....
+            .#..
+            ....
+            ....
+        "
+        .parse()?;
+
+        let turned = model.transformed(Symmetry::RotateY(1));
+        assert!(turned.is_filled(1, 0, 2) && turned.is_filled(2, 0, 2));
+        assert!(turned.is_filled(1, 0, 1) && turned.is_filled(1, 1, 2));
+        for symmetry in [Symmetry::RotateY(3), Symmetry::MirrorX, Symmetry::MirrorZ] {
+            assert_eq!(
+                model,
+                model.transformed(symmetry).transformed(symmetry.inverse())
+            );
+        }
+
+        assert_eq!(Some(([1, 0, 1], [2, 1, 2])), model.bounds());
+        let cropped = model.cropped();
+        assert_eq!(2, cropped.r);
+        assert_eq!(model, cropped.embedded(4, [1, 0, 1])?);
+        assert!(cropped.embedded(2, [1, 0, 0]).is_err());
+        assert_eq!(model, model.translated([1, 0, 0])?.translated([-1, 0, 0])?);
+        assert!(model.translated([0, 0, 2]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,415 @@
+use crate::{CellState, Matrix};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// Palette index written for every voxel, white in the default palette.
+const COLOR: u8 = 1;
+const VERSION: u32 = 150;
+
+/// Model of a `.vox` file, in MagicaVoxel coordinates where z is up.
+struct Model {
+    size: [i64; 3],
+    voxels: Vec<[i64; 3]>,
+}
+
+/// Scene graph node, rotations are ignored.
+enum Node {
+    Transform { child: i32, translation: [i64; 3] },
+    Group { children: Vec<i32> },
+    Shape { models: Vec<i32> },
+}
+
+/// Reads a MagicaVoxel `.vox` file. Models are placed by the translations
+/// of the scene graph when there is one and overlap at the origin otherwise.
+/// The box around them becomes a model of the resolution of its longest
+/// side, with the z axis of MagicaVoxel as our y axis. Voxels touching the
+/// x=0 or z=0 side of the box are shifted off it, and the resolution grows
+/// until the x=R-1, z=R-1 and y=R-1 planes are free, as bots need them.
+pub fn read_vox(reader: &mut impl Read) -> anyhow::Result<Matrix> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    let mut file = Bytes::new(&bytes);
+
+    if file.take(4)? != b"VOX " {
+        return Err(anyhow::anyhow!("Not a vox file"));
+    }
+    file.u32()?;
+    let (id, _, mut chunks) = file.chunk()?;
+    if id != b"MAIN" {
+        return Err(anyhow::anyhow!("Vox file doesn't start with a MAIN chunk"));
+    }
+
+    let mut models = vec![];
+    let mut size = None;
+    let mut nodes = HashMap::new();
+    while !chunks.is_empty() {
+        let (id, mut content, _) = chunks.chunk()?;
+        match id {
+            b"SIZE" => size = Some([content.i64()?, content.i64()?, content.i64()?]),
+            b"XYZI" => {
+                let size = size
+                    .take()
+                    .ok_or_else(|| anyhow::anyhow!("XYZI chunk without a SIZE chunk"))?;
+                let count = content.u32()?;
+                let voxels = (0..count)
+                    .map(|_| {
+                        let voxel = content.take(4)?;
+                        Ok([voxel[0] as i64, voxel[1] as i64, voxel[2] as i64])
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                models.push(Model { size, voxels });
+            }
+            b"nTRN" => {
+                let id = content.i32()?;
+                content.dict()?;
+                let child = content.i32()?;
+                // Reserved id and layer.
+                content.take(8)?;
+                let frames = content.u32()?;
+                let mut translation = [0; 3];
+                if frames > 0 {
+                    if let Some(value) = content.dict()?.get("_t") {
+                        let values = value
+                            .split_whitespace()
+                            .map(str::parse)
+                            .collect::<Result<Vec<i64>, _>>()?;
+                        translation = values
+                            .try_into()
+                            .map_err(|_| anyhow::anyhow!("Translation {value:?} of node {id}"))?;
+                    }
+                }
+                nodes.insert(id, Node::Transform { child, translation });
+            }
+            b"nGRP" => {
+                let id = content.i32()?;
+                content.dict()?;
+                let count = content.u32()?;
+                let children = (0..count)
+                    .map(|_| content.i32())
+                    .collect::<anyhow::Result<_>>()?;
+                nodes.insert(id, Node::Group { children });
+            }
+            b"nSHP" => {
+                let id = content.i32()?;
+                content.dict()?;
+                let count = content.u32()?;
+                let models = (0..count)
+                    .map(|_| {
+                        let model = content.i32()?;
+                        content.dict()?;
+                        Ok(model)
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                nodes.insert(id, Node::Shape { models });
+            }
+            _ => {}
+        }
+    }
+
+    let mut placements = vec![];
+    if nodes.contains_key(&0) {
+        place(&nodes, 0, [0; 3], 0, &mut placements)?;
+    } else {
+        placements.extend((0..models.len() as i32).map(|model| (model, None)));
+    }
+    if placements.is_empty() {
+        return Err(anyhow::anyhow!("Vox file places no model"));
+    }
+
+    // Scene translations are of model centers.
+    let mut voxels = vec![];
+    let mut low = [i64::MAX; 3];
+    let mut high = [i64::MIN; 3];
+    for (index, translation) in placements {
+        let model = usize::try_from(index)
+            .ok()
+            .and_then(|index| models.get(index))
+            .ok_or_else(|| anyhow::anyhow!("Scene refers to missing model {index}"))?;
+        let corner: [i64; 3] = match translation {
+            Some(translation) => [0, 1, 2].map(|axis| translation[axis] - model.size[axis] / 2),
+            None => [0; 3],
+        };
+        for axis in 0..3 {
+            low[axis] = low[axis].min(corner[axis]);
+            high[axis] = high[axis].max(corner[axis] + model.size[axis]);
+        }
+        voxels.extend(
+            model
+                .voxels
+                .iter()
+                .map(|voxel| [0, 1, 2].map(|axis| corner[axis] + voxel[axis])),
+        );
+    }
+
+    let voxels = voxels
+        .into_iter()
+        .map(|voxel| [0, 1, 2].map(|axis| (voxel[axis] - low[axis]) as usize))
+        .collect::<Vec<_>>();
+    // MagicaVoxel x and y are our x and z, only those get shifted.
+    let shift = [0, 1, 2].map(|axis| {
+        let touches = voxels.iter().any(|voxel| voxel[axis] == 0);
+        usize::from(axis < 2 && touches)
+    });
+    let fits = voxels
+        .iter()
+        .flat_map(|voxel| [0, 1, 2].map(|axis| voxel[axis] + shift[axis] + 2))
+        .max()
+        .unwrap_or(0);
+    let size = (0..3)
+        .map(|axis| high[axis] - low[axis])
+        .max()
+        .unwrap_or(0)
+        .max(0) as usize;
+
+    let mut matrix = Matrix::new(size.max(fits));
+    for voxel in voxels {
+        let [x, y, z] = [0, 1, 2].map(|axis| voxel[axis] + shift[axis]);
+        matrix.set(x, z, y, CellState::Fill);
+    }
+    Ok(matrix)
+}
+
+/// Collects the models under `id` with their translations.
+fn place(
+    nodes: &HashMap<i32, Node>,
+    id: i32,
+    translation: [i64; 3],
+    depth: usize,
+    placements: &mut Vec<(i32, Option<[i64; 3]>)>,
+) -> anyhow::Result<()> {
+    if depth > nodes.len() {
+        return Err(anyhow::anyhow!("Scene graph has a cycle"));
+    }
+
+    match nodes.get(&id) {
+        Some(Node::Transform {
+            child,
+            translation: offset,
+        }) => {
+            let translation = [0, 1, 2].map(|axis| translation[axis] + offset[axis]);
+            place(nodes, *child, translation, depth + 1, placements)
+        }
+        Some(Node::Group { children }) => children
+            .iter()
+            .try_for_each(|child| place(nodes, *child, translation, depth + 1, placements)),
+        Some(Node::Shape { models }) => {
+            placements.extend(models.iter().map(|model| (*model, Some(translation))));
+            Ok(())
+        }
+        None => Err(anyhow::anyhow!("Scene refers to missing node {id}")),
+    }
+}
+
+/// Writes `matrix` as a single model `.vox` file using the default palette,
+/// our y axis becoming the z axis of MagicaVoxel.
+pub fn write_vox(writer: &mut impl Write, matrix: &Matrix) -> anyhow::Result<()> {
+    if matrix.r > 256 {
+        return Err(anyhow::anyhow!(
+            "Vox models are at most 256 voxels wide, got {}",
+            matrix.r
+        ));
+    }
+
+    let voxels = matrix
+        .iter()
+        .filter(|cell| cell.state == CellState::Fill)
+        .flat_map(|cell| [cell.x as u8, cell.z as u8, cell.y as u8, COLOR])
+        .collect::<Vec<_>>();
+
+    let mut size = vec![];
+    for _ in 0..3 {
+        size.extend((matrix.r as u32).to_le_bytes());
+    }
+    let mut xyzi = ((voxels.len() / 4) as u32).to_le_bytes().to_vec();
+    xyzi.extend(voxels);
+
+    let mut children = vec![];
+    write_chunk(&mut children, b"SIZE", &size, &[])?;
+    write_chunk(&mut children, b"XYZI", &xyzi, &[])?;
+
+    writer.write_all(b"VOX ")?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    write_chunk(writer, b"MAIN", &[], &children)
+}
+
+fn write_chunk(
+    writer: &mut impl Write,
+    id: &[u8; 4],
+    content: &[u8],
+    children: &[u8],
+) -> anyhow::Result<()> {
+    writer.write_all(id)?;
+    writer.write_all(&(content.len() as u32).to_le_bytes())?;
+    writer.write_all(&(children.len() as u32).to_le_bytes())?;
+    writer.write_all(content)?;
+    writer.write_all(children)?;
+    Ok(())
+}
+
+/// Little endian reader over the bytes of a chunk.
+struct Bytes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Bytes<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn take(&mut self, count: usize) -> anyhow::Result<&'a [u8]> {
+        if self.data.len() < count {
+            return Err(anyhow::anyhow!("Vox file ends early"));
+        }
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> anyhow::Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i64(&mut self) -> anyhow::Result<i64> {
+        Ok(self.i32()? as i64)
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let length = self.u32()? as usize;
+        Ok(String::from_utf8(self.take(length)?.to_vec())?)
+    }
+
+    fn dict(&mut self) -> anyhow::Result<HashMap<String, String>> {
+        (0..self.u32()?)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+
+    /// Id, content and children of the next chunk.
+    fn chunk(&mut self) -> anyhow::Result<(&'a [u8], Bytes<'a>, Bytes<'a>)> {
+        let id = self.take(4)?;
+        let content = self.u32()? as usize;
+        let children = self.u32()? as usize;
+        Ok((
+            id,
+            Bytes::new(self.take(content)?),
+            Bytes::new(self.take(children)?),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_chunk(&mut bytes, id, content, &[]).unwrap();
+        bytes
+    }
+
+    fn ints(values: &[i32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn transform(id: i32, child: i32, translation: &str) -> Vec<u8> {
+        let mut content = ints(&[id, 0, child, -1, 0, 1, 1, 2]);
+        content.extend(b"_t");
+        content.extend(ints(&[translation.len() as i32]));
+        content.extend(translation.as_bytes());
+        chunk(b"nTRN", &content)
+    }
+
+    #[test]
+    fn test_vox_round_trip() -> anyhow::Result<()> {
+        let mut matrix = Matrix::new(4);
+        matrix.set(1, 0, 2, CellState::Fill);
+        matrix.set(1, 1, 2, CellState::Fill);
+        matrix.set(2, 2, 1, CellState::Fill);
+
+        let mut bytes = vec![];
+        write_vox(&mut bytes, &matrix)?;
+
+        assert_eq!(matrix, read_vox(&mut Cursor::new(bytes))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_vox_without_models_fails() -> anyhow::Result<()> {
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend(ints(&[150]));
+        write_chunk(&mut bytes, b"MAIN", &[], &[])?;
+
+        assert!(read_vox(&mut Cursor::new(bytes)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_vox_places_models() -> anyhow::Result<()> {
+        let mut children = vec![];
+        for voxel in [[0, 0, 0, 1], [1, 0, 1, 1]] {
+            children.extend(chunk(b"SIZE", &ints(&[2, 2, 2])));
+            children.extend(chunk(b"XYZI", &[ints(&[1]), voxel.to_vec()].concat()));
+        }
+        children.extend(transform(0, 1, "0 0 0"));
+        children.extend(chunk(b"nGRP", &ints(&[1, 0, 2, 2, 4])));
+        children.extend(transform(2, 3, "0 0 1"));
+        children.extend(chunk(b"nSHP", &ints(&[3, 0, 1, 0, 0])));
+        children.extend(transform(4, 5, "4 0 1"));
+        children.extend(chunk(b"nSHP", &ints(&[5, 0, 1, 1, 0])));
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend(ints(&[150]));
+        write_chunk(&mut bytes, b"MAIN", &[], &children)?;
+
+        let matrix = read_vox(&mut Cursor::new(bytes))?;
+
+        // Model boxes span x -1..5, y -1..1 and z 0..2, voxels are shifted
+        // off the x=0 and z=0 planes.
+        assert_eq!(8, matrix.r);
+        let filled = matrix
+            .iter()
+            .filter(|cell| cell.state == CellState::Fill)
+            .map(|cell| (cell.x, cell.y, cell.z))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(1, 0, 1), (6, 1, 1)], filled);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_vox_keeps_border_planes_free() -> anyhow::Result<()> {
+        // Opposite corners of a 3³ model, touching every side of its box.
+        let mut children = chunk(b"SIZE", &ints(&[3, 3, 3]));
+        let voxels = [ints(&[2]), vec![0, 0, 0, 1, 2, 2, 2, 1]].concat();
+        children.extend(chunk(b"XYZI", &voxels));
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend(ints(&[150]));
+        write_chunk(&mut bytes, b"MAIN", &[], &children)?;
+
+        let matrix = read_vox(&mut Cursor::new(bytes))?;
+
+        assert_eq!(5, matrix.r);
+        let filled = matrix
+            .iter()
+            .filter(|cell| cell.state == CellState::Fill)
+            .map(|cell| (cell.x, cell.y, cell.z))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(1, 0, 1), (3, 2, 3)], filled);
+        for (x, y, z) in filled {
+            assert!((1..matrix.r - 1).contains(&x));
+            assert!((1..matrix.r - 1).contains(&z));
+            assert!(y < matrix.r - 1);
+        }
+        Ok(())
+    }
+}
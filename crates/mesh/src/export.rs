@@ -0,0 +1,242 @@
+use mdl::Matrix;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Rectangle of voxel faces, corners counter-clockwise seen from outside.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quad {
+    pub corners: [[usize; 3]; 4],
+    pub normal: [i8; 3],
+}
+
+/// Faces between Full voxels and Void voxels or the outside of the model,
+/// coplanar neighbours merged greedily into rectangles.
+pub fn exposed_faces(matrix: &Matrix) -> Vec<Quad> {
+    let r = matrix.r;
+    let filled = |position: [usize; 3]| matrix.is_filled(position[0], position[1], position[2]);
+    let mut quads = vec![];
+
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        for positive in [false, true] {
+            for slice in 0..r {
+                let mut mask = vec![false; r * r];
+                for i in 0..r {
+                    for j in 0..r {
+                        let mut position = [0; 3];
+                        (position[axis], position[u], position[v]) = (slice, i, j);
+                        let mut neighbour = position;
+                        let outside = match positive {
+                            true => slice + 1 == r,
+                            false => slice == 0,
+                        };
+                        if !outside {
+                            neighbour[axis] = if positive { slice + 1 } else { slice - 1 };
+                        }
+                        mask[i * r + j] = filled(position) && (outside || !filled(neighbour));
+                    }
+                }
+
+                let plane = if positive { slice + 1 } else { slice };
+                for (i, j, width, height) in merge_rectangles(&mut mask, r) {
+                    let corner = |i: usize, j: usize| {
+                        let mut corner = [0; 3];
+                        (corner[axis], corner[u], corner[v]) = (plane, i, j);
+                        corner
+                    };
+                    let mut corners = [
+                        corner(i, j),
+                        corner(i + width, j),
+                        corner(i + width, j + height),
+                        corner(i, j + height),
+                    ];
+                    let mut normal = [0; 3];
+                    normal[axis] = if positive { 1 } else { -1 };
+                    if !positive {
+                        corners.reverse();
+                    }
+                    quads.push(Quad { corners, normal });
+                }
+            }
+        }
+    }
+
+    quads
+}
+
+/// Covers the set cells of a `size`² mask with rectangles `(i, j, width,
+/// height)`, growing each one along `i` first, then along `j` while the
+/// whole row is set. The mask is cleared.
+fn merge_rectangles(mask: &mut [bool], size: usize) -> Vec<(usize, usize, usize, usize)> {
+    let mut rectangles = vec![];
+
+    for j in 0..size {
+        for i in 0..size {
+            if !mask[i * size + j] {
+                continue;
+            }
+
+            let width = (i..size).take_while(|i| mask[i * size + j]).count();
+            let height = (j..size)
+                .take_while(|j| (i..i + width).all(|i| mask[i * size + j]))
+                .count();
+            for di in i..i + width {
+                for dj in j..j + height {
+                    mask[di * size + dj] = false;
+                }
+            }
+            rectangles.push((i, j, width, height));
+        }
+    }
+
+    rectangles
+}
+
+/// Writes the exposed faces as binary STL, two triangles per rectangle, in
+/// voxel units.
+pub fn write_stl(writer: &mut impl Write, matrix: &Matrix) -> anyhow::Result<()> {
+    let quads = exposed_faces(matrix);
+    writer.write_all(&[0; 80])?;
+    writer.write_all(&(2 * quads.len() as u32).to_le_bytes())?;
+
+    for quad in &quads {
+        let [a, b, c, d] = quad.corners;
+        for triangle in [[a, b, c], [a, c, d]] {
+            for value in quad.normal {
+                writer.write_all(&(value as f32).to_le_bytes())?;
+            }
+            for value in triangle.iter().flatten() {
+                writer.write_all(&(*value as f32).to_le_bytes())?;
+            }
+            writer.write_all(&[0, 0])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the exposed faces as an OBJ file of shared vertices and quads.
+pub fn write_obj(writer: &mut impl Write, matrix: &Matrix) -> anyhow::Result<()> {
+    let (vertices, faces) = index_vertices(&exposed_faces(matrix));
+
+    for [x, y, z] in &vertices {
+        writeln!(writer, "v {x} {y} {z}")?;
+    }
+    // OBJ indices start at 1.
+    for [a, b, c, d] in faces {
+        writeln!(writer, "f {} {} {} {}", a + 1, b + 1, c + 1, d + 1)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the exposed faces as an ASCII PLY file of shared vertices and
+/// quads.
+pub fn write_ply(writer: &mut impl Write, matrix: &Matrix) -> anyhow::Result<()> {
+    let (vertices, faces) = index_vertices(&exposed_faces(matrix));
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "element vertex {}", vertices.len())?;
+    for axis in ["x", "y", "z"] {
+        writeln!(writer, "property float {axis}")?;
+    }
+    writeln!(writer, "element face {}", faces.len())?;
+    writeln!(writer, "property list uchar int vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for [x, y, z] in &vertices {
+        writeln!(writer, "{x} {y} {z}")?;
+    }
+    for [a, b, c, d] in faces {
+        writeln!(writer, "4 {a} {b} {c} {d}")?;
+    }
+
+    Ok(())
+}
+
+/// Writes the exposed faces of `matrix` to `path`, picking the format from
+/// the extension.
+pub fn export(path: &Path, matrix: &Matrix) -> anyhow::Result<()> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    match extension.as_deref() {
+        Some("stl") => write_stl(&mut writer, matrix)?,
+        Some("obj") => write_obj(&mut writer, matrix)?,
+        Some("ply") => write_ply(&mut writer, matrix)?,
+        _ => return Err(anyhow::anyhow!("Unknown mesh format {path:?}")),
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Deduplicates the corners of `quads`, returning the vertices and the
+/// faces as indices into them.
+fn index_vertices(quads: &[Quad]) -> (Vec<[usize; 3]>, Vec<[usize; 4]>) {
+    let mut indices = HashMap::new();
+    let mut vertices = vec![];
+    let faces = quads
+        .iter()
+        .map(|quad| {
+            quad.corners.map(|corner| {
+                *indices.entry(corner).or_insert_with(|| {
+                    vertices.push(corner);
+                    vertices.len() - 1
+                })
+            })
+        })
+        .collect();
+
+    (vertices, faces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_obj, read_stl};
+    use mdl::CellState;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_exposed_faces_are_merged() -> anyhow::Result<()> {
+        let mut matrix = Matrix::new(8);
+        for x in 1..4 {
+            for y in 0..3 {
+                for z in 1..4 {
+                    matrix.set(x, y, z, CellState::Fill);
+                }
+            }
+        }
+        // Cube with a voxel on top of one corner.
+        matrix.set(1, 3, 1, CellState::Fill);
+
+        let quads = exposed_faces(&matrix);
+        // The cube's 6 sides with its top split in two around the extra
+        // voxel, which shows 5 faces.
+        assert_eq!(6 + 1 + 5, quads.len());
+
+        let mut stl = vec![];
+        write_stl(&mut stl, &matrix)?;
+        assert_eq!(
+            2 * quads.len(),
+            read_stl(&mut Cursor::new(stl))?.triangles.len()
+        );
+
+        let mut obj = vec![];
+        write_obj(&mut obj, &matrix)?;
+        assert_eq!(
+            2 * quads.len(),
+            read_obj(&mut Cursor::new(obj))?.triangles.len()
+        );
+
+        let mut ply = vec![];
+        write_ply(&mut ply, &matrix)?;
+        assert!(String::from_utf8(ply)?.contains("element face 12\n"));
+        Ok(())
+    }
+}
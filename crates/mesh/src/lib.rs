@@ -0,0 +1,92 @@
+mod export;
+mod stl;
+mod voxelize;
+
+pub use export::{export, exposed_faces, write_obj, write_ply, write_stl, Quad};
+pub use stl::read_stl;
+pub use voxelize::{voxelize, Rasterization};
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+pub type Vertex = [f32; 3];
+pub type Triangle = [Vertex; 3];
+
+/// Triangle soup, without normals or materials.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub triangles: Vec<Triangle>,
+}
+
+impl Mesh {
+    /// Smallest and largest coordinates of every axis, `None` when empty.
+    pub fn bounds(&self) -> Option<(Vertex, Vertex)> {
+        let mut vertices = self.triangles.iter().flatten();
+        let first = *vertices.next()?;
+        Some(vertices.fold((first, first), |(mut min, mut max), vertex| {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex[axis]);
+                max[axis] = max[axis].max(vertex[axis]);
+            }
+            (min, max)
+        }))
+    }
+}
+
+/// Reads the triangles of an OBJ file, faces with more than 3 vertices are
+/// triangulated and materials are ignored.
+pub fn read_obj(reader: &mut impl BufRead) -> anyhow::Result<Mesh> {
+    let options = tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ignore_points: true,
+        ignore_lines: true,
+    };
+    let (models, _) =
+        tobj::load_obj_buf(reader, &options, |_| Err(tobj::LoadError::OpenFileFailed))?;
+
+    let mut triangles = vec![];
+    for model in models {
+        let positions = &model.mesh.positions;
+        let vertex = |index: u32| {
+            let i = index as usize * 3;
+            [positions[i], positions[i + 1], positions[i + 2]]
+        };
+        for face in model.mesh.indices.chunks_exact(3) {
+            triangles.push([vertex(face[0]), vertex(face[1]), vertex(face[2])]);
+        }
+    }
+
+    Ok(Mesh { triangles })
+}
+
+/// Reads an STL or OBJ file, picking the format from the extension.
+pub fn load(path: &Path) -> anyhow::Result<Mesh> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let mut reader = BufReader::new(File::open(path)?);
+
+    match extension.as_deref() {
+        Some("stl") => read_stl(&mut reader),
+        Some("obj") => read_obj(&mut reader),
+        _ => Err(anyhow::anyhow!("Unknown mesh format {path:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_obj_triangulates_quads() -> anyhow::Result<()> {
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n";
+        let mesh = read_obj(&mut Cursor::new(obj))?;
+
+        assert_eq!(2, mesh.triangles.len());
+        assert_eq!(Some(([0.0; 3], [1.0, 1.0, 0.0])), mesh.bounds());
+        Ok(())
+    }
+}
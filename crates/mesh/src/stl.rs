@@ -0,0 +1,99 @@
+use crate::{Mesh, Triangle, Vertex};
+use std::io::BufRead;
+
+/// Reads a binary or ASCII STL file. ASCII files start with `solid`, but so
+/// do some binary ones, so the triangle count of the binary header decides.
+pub fn read_stl(reader: &mut impl BufRead) -> anyhow::Result<Mesh> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+
+    let binary_size = bytes
+        .get(80..84)
+        .map(|count| 84 + 50 * u32::from_le_bytes(count.try_into().unwrap()) as usize);
+    if binary_size == Some(bytes.len()) {
+        return read_binary(&bytes);
+    }
+    if bytes.starts_with(b"solid") {
+        return read_ascii(std::str::from_utf8(&bytes)?);
+    }
+    Err(anyhow::anyhow!("Not an STL file"))
+}
+
+/// 80 bytes of header, a triangle count, then per triangle a normal, three
+/// vertices and a 2 bytes attribute, all little endian.
+fn read_binary(bytes: &[u8]) -> anyhow::Result<Mesh> {
+    let vertex = |at: &[u8]| -> Vertex {
+        let float = |i: usize| f32::from_le_bytes(at[i * 4..i * 4 + 4].try_into().unwrap());
+        [float(0), float(1), float(2)]
+    };
+
+    let triangles = bytes[84..]
+        .chunks_exact(50)
+        .map(|facet| {
+            [
+                vertex(&facet[12..]),
+                vertex(&facet[24..]),
+                vertex(&facet[36..]),
+            ]
+        })
+        .collect();
+
+    Ok(Mesh { triangles })
+}
+
+fn read_ascii(text: &str) -> anyhow::Result<Mesh> {
+    let mut triangles = vec![];
+    let mut vertices = vec![];
+
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("vertex") => {
+                let mut vertex = [0.0; 3];
+                for coordinate in vertex.iter_mut() {
+                    *coordinate = words
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("Missing coordinate in {line:?}"))?
+                        .parse()?;
+                }
+                vertices.push(vertex);
+            }
+            Some("endfacet") => {
+                let triangle: Triangle = vertices
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("Facet with {} vertices", vertices.len()))?;
+                triangles.push(triangle);
+                vertices.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Mesh { triangles })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_stl_formats() -> anyhow::Result<()> {
+        let ascii = "solid t\n facet normal 0 0 1\n  outer loop\n   vertex 0 0 0\n   \
+                     vertex 1 0 0\n   vertex 0 1 0\n  endloop\n endfacet\nendsolid t\n";
+        let mut binary = vec![0u8; 80];
+        binary.extend(1u32.to_le_bytes());
+        for value in [
+            0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        ] {
+            binary.extend(value.to_le_bytes());
+        }
+        binary.extend([0, 0]);
+
+        let expected = vec![[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]];
+        assert_eq!(expected, read_stl(&mut Cursor::new(ascii))?.triangles);
+        assert_eq!(expected, read_stl(&mut Cursor::new(binary))?.triangles);
+        Ok(())
+    }
+}
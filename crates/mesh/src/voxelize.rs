@@ -0,0 +1,319 @@
+use crate::{Mesh, Vertex};
+use mdl::{CellState, Matrix, NEIGHBOURS};
+
+/// How the triangles of a mesh turn into voxels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rasterization {
+    /// Everything inside the surface, the mesh has to be closed.
+    Solid,
+    /// Only the voxels the surface passes through.
+    Shell,
+}
+
+/// Gap kept between the model and the sides of its box, so faces lying on
+/// them don't spill into the next voxel.
+const INSET: f32 = 1e-3;
+/// Nudges rays off voxel centers along x and z, by different amounts so
+/// they don't run along mesh edges built on the same grid or its diagonals.
+const RAY_OFFSET: (f32, f32) = (1.4142e-3, 1.7320e-3);
+
+/// Scales `mesh` uniformly to fit a model of resolution `r`, centered on the
+/// floor and leaving the borders bots need to go around it free, then
+/// rasterizes it. Voxels left floating are supported by columns down to the
+/// rest of the model or the floor, so the result is grounded.
+pub fn voxelize(mesh: &Mesh, r: usize, rasterization: Rasterization) -> anyhow::Result<Matrix> {
+    if r < 3 {
+        return Err(anyhow::anyhow!("Resolution {r} leaves no room for a model"));
+    }
+    let (min, max) = mesh
+        .bounds()
+        .ok_or_else(|| anyhow::anyhow!("Mesh has no triangles"))?;
+
+    // Full voxels live in 1..=r-2 along x and z, and 0..=r-2 along y.
+    let size = [r - 2, r - 1, r - 2];
+    let scale = (0..3)
+        .filter(|axis| max[*axis] > min[*axis])
+        .map(|axis| (size[axis] as f32 - 2.0 * INSET) / (max[axis] - min[axis]))
+        .reduce(f32::min)
+        .unwrap_or(1.0);
+    let margin = [0, 1, 2].map(|axis| {
+        let extent = (max[axis] - min[axis]) * scale;
+        (size[axis] as f32 - extent).max(0.0) / 2.0
+    });
+    let transform = |vertex: &Vertex| -> Vertex {
+        [
+            (vertex[0] - min[0]) * scale + margin[0],
+            (vertex[1] - min[1]) * scale + INSET,
+            (vertex[2] - min[2]) * scale + margin[2],
+        ]
+    };
+    let triangles = mesh
+        .triangles
+        .iter()
+        .map(|triangle| triangle.each_ref().map(transform))
+        .collect::<Vec<_>>();
+
+    let mut voxels = Voxels::new(size);
+    if rasterization == Rasterization::Solid {
+        fill_inside(&mut voxels, &triangles);
+    }
+    for triangle in &triangles {
+        fill_surface(&mut voxels, triangle);
+    }
+
+    let mut matrix = Matrix::new(r);
+    for (x, y, z) in voxels.filled() {
+        matrix.set(x + 1, y, z + 1, CellState::Fill);
+    }
+    ground(&mut matrix);
+    Ok(matrix)
+}
+
+/// Occupancy of the box the model is scaled to.
+struct Voxels {
+    size: [usize; 3],
+    filled: Vec<bool>,
+}
+
+impl Voxels {
+    fn new(size: [usize; 3]) -> Self {
+        Self {
+            size,
+            filled: vec![false; size.iter().product()],
+        }
+    }
+
+    /// Fills the voxel containing `point`, points on the far faces of the
+    /// box belong to the last voxel.
+    fn fill_at(&mut self, point: &Vertex) {
+        let [x, y, z] =
+            [0, 1, 2].map(|axis| (point[axis].max(0.0) as usize).min(self.size[axis] - 1));
+        self.fill(x, y, z);
+    }
+
+    fn fill(&mut self, x: usize, y: usize, z: usize) {
+        self.filled[(x * self.size[1] + y) * self.size[2] + z] = true;
+    }
+
+    fn filled(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        let [_, height, depth] = self.size;
+        self.filled
+            .iter()
+            .enumerate()
+            .filter(|(_, filled)| **filled)
+            .map(move |(index, _)| {
+                (
+                    index / (height * depth),
+                    (index / depth) % height,
+                    index % depth,
+                )
+            })
+    }
+}
+
+/// Casts a ray up through every column and fills the voxels whose center
+/// lies between an odd and the following even surface crossing.
+fn fill_inside(voxels: &mut Voxels, triangles: &[[Vertex; 3]]) {
+    let [width, height, depth] = voxels.size;
+    let mut crossings = vec![vec![]; width * depth];
+
+    for [a, b, c] in triangles {
+        let (low_x, high_x) = (a[0].min(b[0]).min(c[0]), a[0].max(b[0]).max(c[0]));
+        let (low_z, high_z) = (a[2].min(b[2]).min(c[2]), a[2].max(b[2]).max(c[2]));
+        let columns = |low: f32, high: f32, count: usize| {
+            let first = (low - 0.5).ceil().max(0.0) as usize;
+            let last = ((high - 0.5).floor().max(-1.0) + 1.0) as usize;
+            first..last.min(count)
+        };
+
+        for x in columns(low_x, high_x, width) {
+            for z in columns(low_z, high_z, depth) {
+                let ray = (x as f32 + 0.5 + RAY_OFFSET.0, z as f32 + 0.5 + RAY_OFFSET.1);
+                if let Some(y) = crossing(a, b, c, ray) {
+                    crossings[x * depth + z].push(y);
+                }
+            }
+        }
+    }
+
+    for (column, ys) in crossings.iter_mut().enumerate() {
+        ys.sort_by(f32::total_cmp);
+        for pair in ys.chunks_exact(2) {
+            let first = (pair[0] - 0.5).ceil().max(0.0) as usize;
+            let last = ((pair[1] - 0.5).floor() + 1.0).max(0.0) as usize;
+            for y in first..last.min(height) {
+                voxels.fill(column / depth, y, column % depth);
+            }
+        }
+    }
+}
+
+/// Height at which the vertical line through `ray` crosses the triangle.
+fn crossing(a: &Vertex, b: &Vertex, c: &Vertex, (x, z): (f32, f32)) -> Option<f32> {
+    let area = (b[0] - a[0]) * (c[2] - a[2]) - (c[0] - a[0]) * (b[2] - a[2]);
+    if area == 0.0 {
+        return None;
+    }
+
+    let u = ((b[0] - x) * (c[2] - z) - (c[0] - x) * (b[2] - z)) / area;
+    let v = ((c[0] - x) * (a[2] - z) - (a[0] - x) * (c[2] - z)) / area;
+    let w = 1.0 - u - v;
+    (u >= 0.0 && v >= 0.0 && w >= 0.0).then(|| u * a[1] + v * b[1] + w * c[1])
+}
+
+/// Fills the voxels a triangle passes through, sampling it at least twice
+/// per voxel along every edge.
+fn fill_surface(voxels: &mut Voxels, [a, b, c]: &[Vertex; 3]) {
+    let length = |p: &Vertex, q: &Vertex| {
+        ((p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) + (p[2] - q[2]).powi(2)).sqrt()
+    };
+    let longest = length(a, b).max(length(b, c)).max(length(c, a));
+    let samples = ((longest * 2.0).ceil() as usize).max(1);
+
+    for i in 0..=samples {
+        for j in 0..=samples - i {
+            let (s, t) = (i as f32 / samples as f32, j as f32 / samples as f32);
+            let point =
+                [0, 1, 2].map(|axis| a[axis] + (b[axis] - a[axis]) * s + (c[axis] - a[axis]) * t);
+            voxels.fill_at(&point);
+        }
+    }
+}
+
+/// Adds columns under floating voxels, lowest first, until every Full voxel
+/// is connected to the floor. Connectivity is computed once and grown as
+/// columns join components to it.
+fn ground(matrix: &mut Matrix) {
+    let r = matrix.r;
+    let index = |(x, y, z): (usize, usize, usize)| (x * r + y) * r + z;
+    let order = matrix.fill_order();
+    let mut grounded = vec![false; r * r * r];
+    for voxel in &order.grounded {
+        grounded[index(*voxel)] = true;
+    }
+
+    // The lowest floating voxel not grounded yet rests on a grounded one or
+    // on nothing, so its column grounds the whole component.
+    for (x, y, z) in order.floating {
+        if grounded[index((x, y, z))] {
+            continue;
+        }
+
+        let mut queue = vec![(x, y, z)];
+        for below in (0..y).rev() {
+            if matrix.is_filled(x, below, z) {
+                break;
+            }
+            matrix.set(x, below, z, CellState::Fill);
+            queue.push((x, below, z));
+        }
+        for voxel in &queue {
+            grounded[index(*voxel)] = true;
+        }
+
+        while let Some((x, y, z)) = queue.pop() {
+            for (dx, dy, dz) in NEIGHBOURS {
+                let (nx, ny, nz) = (x as i64 + dx, y as i64 + dy, z as i64 + dz);
+                if [nx, ny, nz].iter().any(|v| *v < 0 || *v >= r as i64) {
+                    continue;
+                }
+
+                let next = (nx as usize, ny as usize, nz as usize);
+                if !grounded[index(next)] && matrix.is_filled(next.0, next.1, next.2) {
+                    grounded[index(next)] = true;
+                    queue.push(next);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Triangle;
+
+    /// Closed box between `low` and `high`.
+    fn cuboid(low: Vertex, high: Vertex) -> Vec<Triangle> {
+        let corner = |i: usize| {
+            [0, 1, 2].map(|axis| {
+                if i >> axis & 1 == 1 {
+                    high[axis]
+                } else {
+                    low[axis]
+                }
+            })
+        };
+        let faces = [
+            [0, 1, 3, 2],
+            [4, 6, 7, 5],
+            [0, 4, 5, 1],
+            [2, 3, 7, 6],
+            [0, 2, 6, 4],
+            [1, 5, 7, 3],
+        ];
+        faces
+            .iter()
+            .flat_map(|[a, b, c, d]| {
+                [
+                    [corner(*a), corner(*b), corner(*c)],
+                    [corner(*a), corner(*c), corner(*d)],
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_voxelize_cube() -> anyhow::Result<()> {
+        let mesh = Mesh {
+            triangles: cuboid([0.0; 3], [1.0; 3]),
+        };
+
+        let solid = voxelize(&mesh, 10, Rasterization::Solid)?;
+        let shell = voxelize(&mesh, 10, Rasterization::Shell)?;
+
+        let count = |matrix: &Matrix| {
+            matrix
+                .iter()
+                .filter(|cell| cell.state == CellState::Fill)
+                .count()
+        };
+        assert_eq!(8 * 8 * 8, count(&solid));
+        assert_eq!(8 * 8 * 8 - 6 * 6 * 6, count(&shell));
+        assert!(solid.is_filled(1, 0, 1) && solid.is_filled(8, 7, 8));
+        assert!(!solid.is_filled(0, 0, 0) && !solid.is_filled(1, 8, 1));
+        assert!(solid.is_grounded() && shell.is_grounded());
+        Ok(())
+    }
+
+    #[test]
+    fn test_voxelize_supports_floating_parts() -> anyhow::Result<()> {
+        let mut triangles = cuboid([0.0; 3], [1.0, 1.0, 1.0]);
+        triangles.extend(cuboid([2.0, 3.0, 0.0], [3.0, 4.0, 1.0]));
+        let mesh = Mesh { triangles };
+
+        let matrix = voxelize(&mesh, 10, Rasterization::Solid)?;
+
+        assert!(matrix.is_grounded());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ground_stacks_components() {
+        // Two floating voxels stacked over each other, the upper one with a
+        // neighbour grounded by the same column.
+        let mut matrix = Matrix::new(6);
+        for (x, y, z) in [(2, 1, 2), (2, 3, 2), (3, 3, 2)] {
+            matrix.set(x, y, z, CellState::Fill);
+        }
+
+        ground(&mut matrix);
+
+        assert!(matrix.is_grounded());
+        let filled = matrix
+            .iter()
+            .filter(|cell| cell.state == CellState::Fill)
+            .count();
+        assert_eq!(5, filled);
+    }
+}
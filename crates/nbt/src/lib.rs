@@ -1,7 +1,7 @@
 use commands::{
     read_bits, Command, Fill, Fission, FusionP, FusionS, GFill, GVoid, LMove, SMove, Void,
 };
-use std::io::BufRead;
+use std::io::{BufRead, Write};
 
 pub fn read_commands(reader: &mut impl BufRead) -> anyhow::Result<Vec<Command>> {
     let mut buffer = [0u8; 1];
@@ -33,12 +33,12 @@ fn parse_command(current: u8, reader: &mut impl BufRead) -> anyhow::Result<Comma
             match exact_command {
                 0b00000100 => {
                     let mut buffer = [0u8; 1];
-                    reader.read(&mut buffer)?;
+                    reader.read_exact(&mut buffer)?;
                     Ok(Command::SMove(SMove::read(current, &buffer)))
                 }
                 0b00001100 => {
                     let mut buffer = [0u8; 1];
-                    reader.read(&mut buffer)?;
+                    reader.read_exact(&mut buffer)?;
                     Ok(Command::LMove(LMove::read(current, &buffer)))
                 }
                 _ => unreachable!(),
@@ -48,28 +48,50 @@ fn parse_command(current: u8, reader: &mut impl BufRead) -> anyhow::Result<Comma
         0b00000110 => Ok(Command::FusionS(FusionS::read(current))),
         0b00000101 => {
             let mut buffer = [0u8; 1];
-            reader.read(&mut buffer)?;
+            reader.read_exact(&mut buffer)?;
             Ok(Command::Fission(Fission::read(current, &buffer)))
         }
         0b00000011 => Ok(Command::Fill(Fill::read(current))),
         0b00000010 => Ok(Command::Void(Void::read(current))),
         0b00000001 => {
             let mut buffer = [0u8; 3];
-            reader.read(&mut buffer)?;
+            reader.read_exact(&mut buffer)?;
             Ok(Command::GFill(GFill::read(current, &buffer)))
         }
         0b00000000 => {
             let mut buffer = [0u8; 3];
-            reader.read(&mut buffer)?;
+            reader.read_exact(&mut buffer)?;
             Ok(Command::GVoid(GVoid::read(current, &buffer)))
         }
         _ => Err(anyhow::anyhow!("Command not recognized {current:b}")),
     }
 }
 
+pub fn write_commands(writer: &mut impl Write, commands: &[Command]) -> anyhow::Result<()> {
+    for command in commands {
+        match command {
+            Command::Halt => writer.write_all(&[0b11111111])?,
+            Command::Wait => writer.write_all(&[0b11111110])?,
+            Command::Flip => writer.write_all(&[0b11111101])?,
+            Command::SMove(m) => writer.write_all(&m.write()?)?,
+            Command::LMove(m) => writer.write_all(&m.write()?)?,
+            Command::FusionP(fusion) => writer.write_all(&[fusion.write()])?,
+            Command::FusionS(fusion) => writer.write_all(&[fusion.write()])?,
+            Command::Fission(fission) => writer.write_all(&fission.write())?,
+            Command::Fill(fill) => writer.write_all(&[fill.write()])?,
+            Command::Void(void) => writer.write_all(&[void.write()])?,
+            Command::GFill(fill) => writer.write_all(&fill.write())?,
+            Command::GVoid(void) => writer.write_all(&void.write())?,
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use commands::Difference;
     use std::collections::HashMap;
     use std::io::BufReader;
 
@@ -82,7 +104,7 @@ mod tests {
         // println!("{:?}", commands);
         let mut counts = HashMap::new();
 
-        fn update(name: &'static str, counts: &mut HashMap<&'static str, usize>) -> () {
+        fn update(name: &'static str, counts: &mut HashMap<&'static str, usize>) {
             counts.entry(name).and_modify(|e| *e += 1).or_insert(1);
         }
 
@@ -106,4 +128,65 @@ mod tests {
         println!("counts: {:#?}", counts);
         Ok(())
     }
+
+    #[test]
+    fn test_write_round_trip() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA001.nbt");
+        let commands = read_commands(&mut BufReader::new(data.as_slice()))?;
+
+        let mut written = vec![];
+        write_commands(&mut written, &commands)?;
+        assert_eq!(data.as_slice(), written.as_slice());
+
+        let others = vec![
+            Command::LMove(LMove {
+                sld1: Difference::short_linear(0, -3, 0),
+                sld2: Difference::short_linear(0, 0, 5),
+            }),
+            Command::GFill(GFill {
+                nd: Difference::near(0, -1, 1),
+                fd: Difference::far(10, -15, 20),
+            }),
+            Command::GVoid(GVoid {
+                nd: Difference::near(1, 0, 0),
+                fd: Difference::far(-5, 0, 30),
+            }),
+            Command::Void(Void {
+                nd: Difference::near(-1, -1, 0),
+            }),
+            Command::FusionP(FusionP {
+                nd: Difference::near(0, 0, -1),
+            }),
+            Command::FusionS(FusionS {
+                nd: Difference::near(0, 0, 1),
+            }),
+        ];
+        let mut written = vec![];
+        write_commands(&mut written, &others)?;
+        assert_eq!(
+            others,
+            read_commands(&mut BufReader::new(written.as_slice()))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_rejects_invalid_moves() {
+        let invalid = [
+            Command::SMove(SMove {
+                lld: Difference::long_linear(1, 1, 0),
+            }),
+            Command::SMove(SMove {
+                lld: Difference::long_linear(0, 16, 0),
+            }),
+            Command::LMove(LMove {
+                sld1: Difference::short_linear(0, 0, 0),
+                sld2: Difference::short_linear(2, 0, 0),
+            }),
+        ];
+
+        for command in invalid {
+            assert!(write_commands(&mut vec![], &[command]).is_err());
+        }
+    }
 }
@@ -0,0 +1,144 @@
+use crate::moves::LONG_LINEAR_MAX;
+use commands::Command;
+use mdl::{CellState, Matrix};
+use state::State;
+
+/// Longest manhattan distance a bot can cover in one step, with an `SMove`.
+const STEP_REACH: usize = LONG_LINEAR_MAX as usize;
+/// Manhattan distance from a bot to the farthest voxel it can fill or void
+/// in one step. A group region spans at most 31 voxels per axis with a bot
+/// near every corner, so some bot is within 15 voxels per axis plus a near
+/// distance of any voxel of it.
+const FILL_REACH: usize = 3 * 15 + 2;
+/// Most voxels a bot can fill in one step, 8 bots sharing a 31³ region.
+const VOXELS_PER_BOT: usize = (31 * 31 * 31usize).div_ceil(8);
+
+/// Energy no trace turning `source` into `target` can beat, split by source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LowerBound {
+    pub steps: usize,
+    /// Low harmonic energy of every step with a single active bot.
+    pub step_energy: i64,
+    /// Filling the voxels missing from `source`, less voiding the extra ones.
+    pub fill_energy: i64,
+    /// Moving a bot to the farthest voxel to change and back to the origin.
+    pub move_energy: i64,
+}
+
+impl LowerBound {
+    pub fn energy(&self) -> i64 {
+        self.step_energy + self.fill_energy + self.move_energy
+    }
+}
+
+/// Distance between the energy of a trace and the lower bound.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gap {
+    pub bound: LowerBound,
+    pub energy: i64,
+}
+
+impl Gap {
+    pub fn absolute(&self) -> i64 {
+        self.energy - self.bound.energy()
+    }
+
+    /// Energy of the trace relative to the bound, 1.0 being optimal.
+    pub fn ratio(&self) -> f64 {
+        self.energy as f64 / self.bound.energy().max(1) as f64
+    }
+}
+
+/// Lower bound on the energy of turning `source` into `target` with at most
+/// `max_bots` bots. Steps are bounded by how far the farthest changed voxel
+/// is from the origin and by how many voxels the bots can change per step.
+pub fn lower_bound(source: &Matrix, target: &Matrix, max_bots: u8) -> LowerBound {
+    let mut fills = 0usize;
+    let mut voids = 0usize;
+    let mut farthest = None;
+
+    for (from, to) in source.iter().zip(target.iter()) {
+        match (&from.state, &to.state) {
+            (CellState::Void, CellState::Fill) => fills += 1,
+            (CellState::Fill, CellState::Void) => voids += 1,
+            _ => continue,
+        }
+        farthest = farthest.max(Some(to.x + to.y + to.z));
+    }
+
+    // Halting takes a step of its own.
+    let (steps, moves) = match farthest {
+        None => (1, 0),
+        Some(distance) => {
+            let out = distance.saturating_sub(FILL_REACH);
+            let travel = 2 * out.div_ceil(STEP_REACH) + 2;
+            let capacity = (max_bots as usize * VOXELS_PER_BOT).max(1);
+            let throughput = (fills + voids).div_ceil(capacity) + 1;
+            (travel.max(throughput), 2 * out)
+        }
+    };
+
+    LowerBound {
+        steps,
+        step_energy: steps as i64 * (3 * source.r.pow(3) as i64 + 20),
+        fill_energy: 12 * fills as i64 - 12 * voids as i64,
+        move_energy: 2 * moves as i64,
+    }
+}
+
+/// Simulates `trace` and compares its energy with the lower bound.
+pub fn gap(
+    source: &Matrix,
+    target: &Matrix,
+    max_bots: u8,
+    trace: &[Command],
+) -> anyhow::Result<Gap> {
+    let mut state = State::new(max_bots, source.clone());
+    state.execute(trace)?;
+    state.check_target(target)?;
+
+    Ok(Gap {
+        bound: lower_bound(source, target, max_bots),
+        energy: state.energy,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_lower_bound_below_default_traces() -> anyhow::Result<()> {
+        let problems: [(&[u8], &[u8]); 2] = [
+            (
+                include_bytes!("../../../data/FA001_tgt.mdl"),
+                include_bytes!("../../../data/FA001.nbt"),
+            ),
+            (
+                include_bytes!("../../../data/FA004_tgt.mdl"),
+                include_bytes!("../../../data/FA004.nbt"),
+            ),
+        ];
+
+        for (model, trace) in problems {
+            let target = mdl::read_matrix(&mut Cursor::new(model))?;
+            let trace = nbt::read_commands(&mut Cursor::new(trace))?;
+            let gap = gap(&Matrix::new(target.r), &target, 20, &trace)?;
+
+            assert!(gap.bound.fill_energy > 0);
+            assert!(gap.absolute() > 0);
+            assert!(gap.ratio() > 1.0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_lower_bound_of_unchanged_model() {
+        let matrix = Matrix::new(10);
+        let bound = lower_bound(&matrix, &matrix, 20);
+
+        assert_eq!(1, bound.steps);
+        assert_eq!(3 * 1000 + 20, bound.energy());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::moves::push_lockstep;
+use crate::pathfinding::find_moves_near;
+use bot::Position;
+use commands::{Command, Difference, Fill, Fission, FusionP, FusionS, GFill, GVoid, Void};
+use mdl::{CellState, Matrix, SparseMatrix, NEIGHBOURS};
+use state::Region;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Number of bots needed to switch every cuboid with one group command.
+pub fn crew_size(cuboids: &[Region], max_bots: u8, r: usize) -> anyhow::Result<usize> {
+    let count = cuboids
+        .iter()
+        .map(|cuboid| 1 << cuboid.dimension())
+        .max()
+        .unwrap_or(1);
+    if count > max_bots as usize || count > r {
+        return Err(anyhow::anyhow!(
+            "Cuboids need {count} bots, only {max_bots} available with R={r}"
+        ));
+    }
+
+    Ok(count)
+}
+
+pub fn for_each_voxel(region: &Region, mut f: impl FnMut(usize, usize, usize)) {
+    for x in region.min.x..=region.max.x {
+        for y in region.min.y..=region.max.y {
+            for z in region.min.z..=region.max.z {
+                f(x as usize, y as usize, z as usize);
+            }
+        }
+    }
+}
+
+fn near_differences() -> impl Iterator<Item = (i32, i32, i32)> {
+    (-1i32..=1)
+        .flat_map(|dx| (-1i32..=1).flat_map(move |dy| (-1i32..=1).map(move |dz| (dx, dy, dz))))
+        .filter(|(dx, dy, dz)| {
+            let mlen = dx.abs() + dy.abs() + dz.abs();
+            mlen > 0 && mlen <= 2
+        })
+}
+
+fn offset(position: &Position, (dx, dy, dz): (i32, i32, i32)) -> (i32, i32, i32) {
+    (
+        position.x as i32 + dx,
+        position.y as i32 + dy,
+        position.z as i32 + dz,
+    )
+}
+
+/// Whether a bot at `from` can still reach the y=R-1 plane, which problems
+/// keep free, once `cuboid` is filled. Searches upwards first so it stays
+/// cheap in the open and only explores a cavity when `from` is sealed in.
+fn escapes(matrix: &SparseMatrix, cuboid: &Region, from: &Position) -> bool {
+    let r = matrix.r() as i32;
+    let is_free = |(x, y, z): (i32, i32, i32)| {
+        [x, y, z].iter().all(|v| (0..r).contains(v))
+            && !matrix.is_filled(x as usize, y as usize, z as usize)
+            && !cuboid.contains(&Position::new(x as u8, y as u8, z as u8))
+    };
+
+    let start = offset(from, (0, 0, 0));
+    if !is_free(start) {
+        return false;
+    }
+    let mut visited = HashSet::from([start]);
+    let mut queue = BinaryHeap::from([(start.1, start)]);
+
+    while let Some((y, voxel)) = queue.pop() {
+        if y == r - 1 {
+            return true;
+        }
+        for (dx, dy, dz) in NEIGHBOURS {
+            let next = (
+                voxel.0 + dx as i32,
+                voxel.1 + dy as i32,
+                voxel.2 + dz as i32,
+            );
+            if is_free(next) && visited.insert(next) {
+                queue.push((next.1, next));
+            }
+        }
+    }
+
+    false
+}
+
+fn difference(from: &Position, to: &Position) -> (i8, i8, i8) {
+    (
+        (to.x as i32 - from.x as i32) as i8,
+        (to.y as i32 - from.y as i32) as i8,
+        (to.z as i32 - from.z as i32) as i8,
+    )
+}
+
+/// Bots moving one at a time while the others wait, with `positions`
+/// indexed by bid - 1 and `matrix` tracking the current model, kept
+/// sparse for the pathfinder.
+pub struct Crew {
+    pub positions: Vec<Position>,
+    pub matrix: SparseMatrix,
+    pub trace: Vec<Command>,
+}
+
+impl Crew {
+    /// Fissions a chain of bots along the x axis from the origin, which
+    /// problems guarantee to be free.
+    pub fn spawn(matrix: Matrix, count: usize) -> Self {
+        let mut crew = Self {
+            positions: vec![Position::zero()],
+            matrix: SparseMatrix::from(&matrix),
+            trace: vec![],
+        };
+
+        for i in 0..count - 1 {
+            let mut plans = vec![vec![]; crew.positions.len()];
+            plans[i].push(Command::Fission(Fission {
+                nd: Difference::near(1, 0, 0),
+                m: (count - 2 - i) as u8,
+            }));
+            push_lockstep(&mut crew.trace, &plans);
+            crew.positions.push(Position::new(i as u8 + 1, 0, 0));
+        }
+
+        crew
+    }
+
+    /// Moves `bot` alone to a position accepted by `goal`, all of which lie
+    /// within `radius` of `center`, while the others wait.
+    pub fn walk(
+        &mut self,
+        bot: usize,
+        (center, radius): (&Position, u32),
+        goal: impl Fn(&Position) -> bool,
+    ) -> anyhow::Result<()> {
+        let blocked = self
+            .positions
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != bot)
+            .map(|(_, position)| position.clone())
+            .collect::<HashSet<_>>();
+
+        let (moves, end) = find_moves_near(
+            &self.matrix,
+            &blocked,
+            &self.positions[bot],
+            center,
+            radius,
+            goal,
+        )
+        .ok_or_else(|| anyhow::anyhow!("No path for bot {}", bot + 1))?;
+
+        let mut plans = vec![vec![]; self.positions.len()];
+        plans[bot] = moves;
+        push_lockstep(&mut self.trace, &plans);
+        self.positions[bot] = end;
+
+        Ok(())
+    }
+
+    /// Places a bot next to every corner of `cuboid` and switches it to
+    /// `state` with a single group command, or a `Fill`/`Void` when the
+    /// cuboid is a single voxel. Bots and stations are kept where they can
+    /// still get out once the cuboid is filled, so closing a cavity never
+    /// traps a bot inside.
+    pub fn set_region(&mut self, cuboid: &Region, state: CellState) -> anyhow::Result<()> {
+        for bot in 0..self.positions.len() {
+            if !escapes(&self.matrix, cuboid, &self.positions[bot]) {
+                let here = self.positions[bot].clone();
+                let matrix = self.matrix.clone();
+                self.walk(bot, (&here, u32::MAX), |position| {
+                    escapes(&matrix, cuboid, position)
+                })?;
+            }
+        }
+
+        let corners = corners(cuboid);
+        let mut stations: Vec<(usize, Position)> = vec![];
+
+        for corner in &corners {
+            let bot = (0..self.positions.len())
+                .filter(|bot| stations.iter().all(|(used, _)| used != bot))
+                .min_by_key(|bot| manhattan(&self.positions[*bot], corner))
+                .unwrap();
+
+            let r = self.matrix.r() as i32;
+            let taken = stations
+                .iter()
+                .map(|(_, station)| station.clone())
+                .collect::<HashSet<_>>();
+            let candidates = near_differences()
+                .map(|d| offset(corner, (-d.0, -d.1, -d.2)))
+                .filter(|p| [p.0, p.1, p.2].iter().all(|v| (0..r).contains(v)))
+                .map(|(x, y, z)| Position::new(x as u8, y as u8, z as u8))
+                .filter(|p| !taken.contains(p) && escapes(&self.matrix, cuboid, p))
+                .collect::<HashSet<_>>();
+
+            self.walk(bot, (corner, 2), |position| candidates.contains(position))?;
+            stations.push((bot, self.positions[bot].clone()));
+        }
+
+        let mut plans = vec![vec![]; self.positions.len()];
+        for ((bot, station), corner) in stations.iter().zip(&corners) {
+            let (dx, dy, dz) = difference(station, corner);
+            let nd = Difference::near(dx, dy, dz);
+
+            let opposite = Position::new(
+                cuboid.min.x + cuboid.max.x - corner.x,
+                cuboid.min.y + cuboid.max.y - corner.y,
+                cuboid.min.z + cuboid.max.z - corner.z,
+            );
+            let (dx, dy, dz) = difference(corner, &opposite);
+            let fd = Difference::far(dx, dy, dz);
+
+            plans[*bot].push(match (&state, corners.len()) {
+                (CellState::Fill, 1) => Command::Fill(Fill { nd }),
+                (CellState::Void, 1) => Command::Void(Void { nd }),
+                (CellState::Fill, _) => Command::GFill(GFill { nd, fd }),
+                (CellState::Void, _) => Command::GVoid(GVoid { nd, fd }),
+            });
+        }
+        push_lockstep(&mut self.trace, &plans);
+        for_each_voxel(cuboid, |x, y, z| self.matrix.set(x, y, z, state.clone()));
+
+        Ok(())
+    }
+
+    /// Lines the bots back up along the x axis and fuses them into the
+    /// first one, leaving it at the origin.
+    pub fn gather(&mut self) -> anyhow::Result<()> {
+        for bot in 0..self.positions.len() {
+            let home = Position::new(bot as u8, 0, 0);
+            self.walk(bot, (&home, 0), |position| *position == home)?;
+        }
+
+        while self.positions.len() > 1 {
+            let last = self.positions.len() - 1;
+            let mut plans = vec![vec![]; self.positions.len()];
+            plans[last - 1].push(Command::FusionP(FusionP {
+                nd: Difference::near(1, 0, 0),
+            }));
+            plans[last].push(Command::FusionS(FusionS {
+                nd: Difference::near(-1, 0, 0),
+            }));
+            push_lockstep(&mut self.trace, &plans);
+            self.positions.pop();
+        }
+
+        Ok(())
+    }
+}
+
+fn corners(cuboid: &Region) -> Vec<Position> {
+    let mut corners = vec![];
+    for x in [cuboid.min.x, cuboid.max.x] {
+        for y in [cuboid.min.y, cuboid.max.y] {
+            for z in [cuboid.min.z, cuboid.max.z] {
+                let corner = Position::new(x, y, z);
+                if !corners.contains(&corner) {
+                    corners.push(corner);
+                }
+            }
+        }
+    }
+    corners
+}
+
+fn manhattan(a: &Position, b: &Position) -> u32 {
+    let (dx, dy, dz) = difference(a, b);
+    dx.unsigned_abs() as u32 + dy.unsigned_abs() as u32 + dz.unsigned_abs() as u32
+}
@@ -0,0 +1,233 @@
+use crate::crew::{crew_size, for_each_voxel, Crew};
+use crate::harmonics::minimize_harmonics;
+use crate::strip_solver::strip_trace;
+use crate::{Cancellation, ProblemKind, Solver, SolverResult, SolverState, SOLVERS};
+use bot::Position;
+use commands::Command;
+use linkme::distributed_slice;
+use mdl::{CellState, Matrix, NEIGHBOURS};
+use state::Region;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// GFill regions can span at most 31 voxels, keep a margin of one.
+pub const MAX_CUBOID_SIDE: usize = 30;
+
+#[distributed_slice(SOLVERS)]
+static GFILL_SOLVER: Solver = Solver {
+    name: "gfill",
+    kinds: &[ProblemKind::Assembly],
+    run: gfill_solver,
+};
+
+fn gfill_solver(state: &SolverState) -> anyhow::Result<SolverResult> {
+    let start = Instant::now();
+    state.expect_kind(ProblemKind::Assembly)?;
+    let mut trace = match gfill_trace(&state.target, state.max_bots, &state.cancellation) {
+        Err(_) if state.cancellation.is_cancelled() => strip_trace(&state.target, state.max_bots)?,
+        trace => trace?,
+    };
+    // Flips are only moved around when there's time left.
+    if !state.cancellation.is_cancelled() {
+        trace = minimize_harmonics(&state.source, state.max_bots, &trace)?;
+    }
+    Ok(SolverResult {
+        elapsed: start.elapsed(),
+        trace,
+    })
+}
+
+/// Assembles `target` one cuboid at a time, placing a bot next to every
+/// corner of the cuboid and filling it with a single `GFill`. Cuboids are
+/// ordered so each one touches the ground or something already filled,
+/// the whole trace runs in High harmonic only when that is impossible.
+pub fn gfill_trace(
+    target: &Matrix,
+    max_bots: u8,
+    cancellation: &Cancellation,
+) -> anyhow::Result<Vec<Command>> {
+    let (cuboids, grounded) =
+        order_cuboids(decompose(target, MAX_CUBOID_SIDE), Matrix::new(target.r));
+    if cuboids.is_empty() {
+        return Ok(vec![Command::Halt]);
+    }
+
+    let bot_count = crew_size(&cuboids, max_bots, target.r)?;
+    let mut crew = Crew::spawn(Matrix::new(target.r), bot_count);
+    if !grounded {
+        crew.trace.insert(0, Command::Flip);
+    }
+
+    for cuboid in &cuboids {
+        cancellation.check()?;
+        crew.set_region(cuboid, CellState::Fill)?;
+    }
+
+    crew.gather()?;
+    if !grounded {
+        crew.trace.push(Command::Flip);
+    }
+    crew.trace.push(Command::Halt);
+
+    Ok(crew.trace)
+}
+
+/// Splits Full voxels into non-overlapping cuboids of at most `max_side`
+/// voxels per side, as regions bots can fill or void.
+pub fn decompose(target: &Matrix, max_side: usize) -> Vec<Region> {
+    target
+        .cuboids(Some(max_side))
+        .into_iter()
+        .map(|cuboid| {
+            let position = |[x, y, z]: [usize; 3]| Position::new(x as u8, y as u8, z as u8);
+            Region::new(&position(cuboid.min), &position(cuboid.max))
+        })
+        .collect()
+}
+
+/// Orders cuboids following the fill order of the whole model, taking the
+/// first one touching the ground or an already placed voxel, starting from
+/// the voxels of `placed`. Returns false when some cuboid had to be placed
+/// floating.
+pub fn order_cuboids(mut cuboids: Vec<Region>, mut placed: Matrix) -> (Vec<Region>, bool) {
+    let mut ordered = Vec::with_capacity(cuboids.len());
+    let mut grounded = true;
+
+    let mut model = placed.clone();
+    for cuboid in &cuboids {
+        for_each_voxel(cuboid, |x, y, z| model.set(x, y, z, CellState::Fill));
+    }
+    let order = model.fill_order();
+    let rank = order
+        .grounded
+        .into_iter()
+        .chain(order.floating)
+        .enumerate()
+        .map(|(rank, voxel)| (voxel, rank))
+        .collect::<HashMap<_, _>>();
+    cuboids.sort_by_key(|cuboid| {
+        let mut first = usize::MAX;
+        for_each_voxel(cuboid, |x, y, z| first = first.min(rank[&(x, y, z)]));
+        first
+    });
+
+    while !cuboids.is_empty() {
+        let next = match cuboids.iter().position(|c| is_supported(c, &placed)) {
+            Some(index) => index,
+            None => {
+                grounded = false;
+                0
+            }
+        };
+
+        let cuboid = cuboids.remove(next);
+        for_each_voxel(&cuboid, |x, y, z| placed.set(x, y, z, CellState::Fill));
+        ordered.push(cuboid);
+    }
+
+    (ordered, grounded)
+}
+
+fn is_supported(cuboid: &Region, placed: &Matrix) -> bool {
+    if cuboid.min.y == 0 {
+        return true;
+    }
+
+    let r = placed.r as i64;
+    let mut supported = false;
+    for_each_voxel(cuboid, |x, y, z| {
+        for (dx, dy, dz) in NEIGHBOURS {
+            let (nx, ny, nz) = (x as i64 + dx, y as i64 + dy, z as i64 + dz);
+            if [nx, ny, nz].iter().all(|v| (0..r).contains(v))
+                && placed.is_filled(nx as usize, ny as usize, nz as usize)
+            {
+                supported = true;
+            }
+        }
+    });
+
+    supported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::State;
+    use std::io::Cursor;
+
+    fn solid_box(r: usize, min: (usize, usize, usize), max: (usize, usize, usize)) -> Matrix {
+        let mut matrix = Matrix::new(r);
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    matrix.set(x, y, z, CellState::Fill);
+                }
+            }
+        }
+        matrix
+    }
+
+    #[test]
+    fn test_decompose_covers_model() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA002_tgt.mdl");
+        let model = mdl::read_matrix(&mut Cursor::new(data))?;
+
+        let mut union = Matrix::new(model.r);
+        for cuboid in decompose(&model, 4) {
+            assert!(cuboid.max.x - cuboid.min.x < 4);
+            assert!(cuboid.max.y - cuboid.min.y < 4);
+            assert!(cuboid.max.z - cuboid.min.z < 4);
+            for_each_voxel(&cuboid, |x, y, z| {
+                assert!(!union.is_filled(x, y, z));
+                union.set(x, y, z, CellState::Fill);
+            });
+        }
+
+        assert_eq!(model, union);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gfill_solver_builds_model() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA003_tgt.mdl");
+        let models = [
+            solid_box(20, (3, 0, 4), (12, 8, 15)),
+            mdl::read_matrix(&mut Cursor::new(data))?,
+        ];
+
+        for model in models {
+            let trace = gfill_trace(&model, 20, &Cancellation::default())?;
+            let mut state = State::new(20, Matrix::new(model.r));
+            state.execute(&trace)?;
+
+            assert!(state.halted);
+            assert_eq!(model, state.matrix);
+        }
+
+        let trace = gfill_trace(
+            &solid_box(20, (3, 0, 4), (12, 8, 15)),
+            20,
+            &Cancellation::default(),
+        )?;
+        let fills = trace
+            .iter()
+            .filter(|command| matches!(command, Command::GFill(_)))
+            .count();
+        assert_eq!(8, fills);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gfill_solver_builds_generated_models() -> anyhow::Result<()> {
+        for family in mdl::Family::ALL {
+            let model = mdl::generate(family, 16, 7)?;
+            let trace = gfill_trace(&model, 20, &Cancellation::default())?;
+            let mut state = State::new(20, Matrix::new(model.r));
+            state.execute(&trace)?;
+
+            assert!(state.halted, "{family:?} doesn't halt");
+            assert_eq!(model, state.matrix, "{family:?} isn't built");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use crate::crew::{crew_size, Crew};
+use crate::gfill_solver::{decompose, order_cuboids, MAX_CUBOID_SIDE};
+use crate::strip_solver::unstrip_trace;
+use crate::{Cancellation, ProblemKind, Solver, SolverResult, SolverState, SOLVERS};
+use commands::Command;
+use linkme::distributed_slice;
+use mdl::{CellState, Matrix};
+use std::time::Instant;
+
+#[distributed_slice(SOLVERS)]
+static GVOID_SOLVER: Solver = Solver {
+    name: "gvoid",
+    kinds: &[ProblemKind::Disassembly],
+    run: gvoid_solver,
+};
+
+fn gvoid_solver(state: &SolverState) -> anyhow::Result<SolverResult> {
+    let start = Instant::now();
+    state.expect_kind(ProblemKind::Disassembly)?;
+    let trace = match gvoid_trace(&state.source, state.max_bots, &state.cancellation) {
+        Err(_) if state.cancellation.is_cancelled() => {
+            unstrip_trace(&state.source, state.max_bots)?
+        }
+        trace => trace?,
+    };
+    Ok(SolverResult {
+        elapsed: start.elapsed(),
+        trace,
+    })
+}
+
+/// Clears `source` with one `GVoid` per cuboid, or a `Void` for single
+/// voxels. Cuboids are removed in the reverse of a grounded fill order, top
+/// first, so whatever remains stays grounded and the trace can run in Low
+/// harmonic.
+pub fn gvoid_trace(
+    source: &Matrix,
+    max_bots: u8,
+    cancellation: &Cancellation,
+) -> anyhow::Result<Vec<Command>> {
+    let (mut cuboids, grounded) =
+        order_cuboids(decompose(source, MAX_CUBOID_SIDE), Matrix::new(source.r));
+    if cuboids.is_empty() {
+        return Ok(vec![Command::Halt]);
+    }
+    cuboids.reverse();
+
+    let mut crew = Crew::spawn(source.clone(), crew_size(&cuboids, max_bots, source.r)?);
+    if !grounded {
+        crew.trace.insert(0, Command::Flip);
+    }
+
+    for cuboid in &cuboids {
+        cancellation.check()?;
+        crew.set_region(cuboid, CellState::Void)?;
+    }
+
+    crew.gather()?;
+    if !grounded {
+        crew.trace.push(Command::Flip);
+    }
+    crew.trace.push(Command::Halt);
+
+    Ok(crew.trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::State;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_gvoid_solver_clears_model() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA004_tgt.mdl");
+        let source = mdl::read_matrix(&mut Cursor::new(data))?;
+
+        let trace = gvoid_trace(&source, 20, &Cancellation::default())?;
+        let mut state = State::new(20, source.clone());
+        state.execute(&trace)?;
+
+        assert!(state.halted);
+        assert!(state.matrix.is_empty());
+        assert!(trace
+            .iter()
+            .any(|command| matches!(command, Command::GVoid(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_gvoid_solver_falls_back_on_strips() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA004_tgt.mdl");
+        let source = mdl::read_matrix(&mut Cursor::new(data))?;
+        let problem = SolverState {
+            target: Matrix::new(source.r),
+            source: source.clone(),
+            max_bots: 20,
+            cancellation: Cancellation::default(),
+        };
+
+        problem.cancellation.cancel();
+        let result = gvoid_solver(&problem)?;
+        let mut state = State::new(20, source);
+        state.execute(&result.trace)?;
+
+        assert!(state.halted);
+        assert!(state.matrix.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use commands::Command;
+use mdl::Matrix;
+use state::State;
+use std::ops::Range;
+
+/// Moves the `Flip`s of a halting trace that starts from `source` so High
+/// harmonic only brackets the steps ending with an ungrounded model. Flips
+/// replace `Wait`s, so an interval is widened up to the nearest step where
+/// some bot waits. The original trace is returned when that is not possible
+/// or not cheaper.
+pub fn minimize_harmonics(
+    source: &Matrix,
+    max_bots: u8,
+    trace: &[Command],
+) -> anyhow::Result<Vec<Command>> {
+    let mut state = State::new(max_bots, source.clone());
+    let mut steps: Vec<Range<usize>> = vec![];
+    let mut grounded = vec![];
+
+    while !state.halted {
+        let start = steps.last().map_or(0, |step| step.end);
+        let end = start + state.current_bot_count;
+        if end > trace.len() {
+            return Err(anyhow::anyhow!("Trace doesn't halt"));
+        }
+
+        state.step(&trace[start..end])?;
+        steps.push(start..end);
+        grounded.push(state.grounded);
+    }
+
+    let mut optimized = trace.to_vec();
+    for command in optimized.iter_mut() {
+        if *command == Command::Flip {
+            *command = Command::Wait;
+        }
+    }
+
+    let waits = steps
+        .iter()
+        .map(|step| step.clone().find(|i| optimized[*i] == Command::Wait))
+        .collect::<Vec<_>>();
+    let Some(flips) = place_flips(&grounded, &waits) else {
+        return Ok(trace.to_vec());
+    };
+    for index in flips {
+        optimized[index] = Command::Flip;
+    }
+
+    // A placement the simulator rejects just means keeping the original.
+    let mut simulation = State::new(max_bots, source.clone());
+    if simulation.execute(&optimized).is_err() {
+        return Ok(trace.to_vec());
+    }
+    if simulation.energy < state.energy && simulation.matrix == state.matrix {
+        Ok(optimized)
+    } else {
+        Ok(trace.to_vec())
+    }
+}
+
+/// Trace indices of the `Wait`s to turn into `Flip`s, given whether the
+/// model is grounded at the end of every step and the first `Wait` of every
+/// step. A step flipping to Low has to end grounded.
+fn place_flips(grounded: &[bool], waits: &[Option<usize>]) -> Option<Vec<usize>> {
+    let mut flips: Vec<(usize, usize)> = vec![];
+    let mut step = 0;
+
+    while step < grounded.len() {
+        if grounded[step] {
+            step += 1;
+            continue;
+        }
+
+        let low_since = flips.last().map_or(0, |(off, _)| off + 1);
+        match (low_since..=step).rev().find(|s| waits[*s].is_some()) {
+            Some(on) => flips.push((on, waits[on]?)),
+            // Nowhere to flip in between, stay in High since the last interval.
+            None => {
+                flips.pop()?;
+            }
+        }
+
+        let off = (step + 1..grounded.len()).find(|s| grounded[*s] && waits[*s].is_some())?;
+        flips.push((off, waits[off]?));
+        step = off + 1;
+    }
+
+    Some(flips.into_iter().map(|(_, index)| index).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_solver::strip_trace;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_place_flips_brackets_ungrounded_steps() {
+        let grounded = [true, true, false, false, true, true, false, true];
+        let waits = [
+            Some(0),
+            Some(1),
+            None,
+            Some(3),
+            None,
+            Some(5),
+            Some(6),
+            Some(7),
+        ];
+
+        assert_eq!(Some(vec![1, 5, 6, 7]), place_flips(&grounded, &waits));
+        assert_eq!(None, place_flips(&grounded, &[None; 8]));
+    }
+
+    #[test]
+    fn test_strip_trace_drops_high_harmonic() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA001_tgt.mdl");
+        let target = mdl::read_matrix(&mut Cursor::new(data))?;
+        let source = Matrix::new(target.r);
+
+        let trace = strip_trace(&target, 20)?;
+        let optimized = minimize_harmonics(&source, 20, &trace)?;
+
+        let mut before = State::new(20, source.clone());
+        before.execute(&trace)?;
+        let mut after = State::new(20, source);
+        after.execute(&optimized)?;
+
+        assert!(after.halted);
+        assert_eq!(target, after.matrix);
+        assert!(after.energy < before.energy);
+        Ok(())
+    }
+}
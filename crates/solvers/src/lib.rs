@@ -1,31 +1,142 @@
+pub mod bounds;
+mod crew;
+pub mod gfill_solver;
+pub mod gvoid_solver;
+pub mod harmonics;
+pub mod moves;
+pub mod pathfinding;
+pub mod peephole;
+pub mod portfolio;
+pub mod reassembly_solver;
+mod reversed_solver;
+pub mod scheduler;
 mod simple_solver;
+pub mod store;
+pub mod strip_solver;
+pub mod transform;
 
+use commands::Command;
 use linkme::distributed_slice;
-use std::time::Duration;
+use mdl::Matrix;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[distributed_slice]
-pub static SOLVERS: [fn(&SolverState) -> anyhow::Result<SolverResult>];
+pub static SOLVERS: [Solver];
 
-#[derive(Debug)]
-pub struct SolverState {}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemKind {
+    Assembly,
+    Disassembly,
+    Reassembly,
+}
+
+/// Problem given to a solver, `source` is empty for assembly problems and
+/// `target` is empty for disassembly ones.
+#[derive(Debug, Clone)]
+pub struct SolverState {
+    pub source: Matrix,
+    pub target: Matrix,
+    pub max_bots: u8,
+    pub cancellation: Cancellation,
+}
+
+impl SolverState {
+    pub fn kind(&self) -> ProblemKind {
+        match (self.source.is_empty(), self.target.is_empty()) {
+            (true, _) => ProblemKind::Assembly,
+            (false, true) => ProblemKind::Disassembly,
+            (false, false) => ProblemKind::Reassembly,
+        }
+    }
+
+    pub fn expect_kind(&self, kind: ProblemKind) -> anyhow::Result<()> {
+        let actual = self.kind();
+        if actual != kind {
+            return Err(anyhow::anyhow!("Solver handles {kind:?}, got {actual:?}"));
+        }
+        Ok(())
+    }
+}
+
+/// Token telling solvers to stop, either once a deadline passes or when
+/// cancelled explicitly. Clones share the explicit cancellation.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Cancellation {
+    /// Same token, also firing at `deadline`.
+    pub fn with_deadline(&self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(self.deadline.map_or(deadline, |own| own.min(deadline))),
+            cancelled: self.cancelled.clone(),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Fails once cancelled, for solvers that have nothing to return yet.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(anyhow::anyhow!("Solver cancelled"));
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
 pub struct SolverResult {
     pub elapsed: Duration,
+    pub trace: Vec<Command>,
 }
 
 pub type SolverType = fn(&SolverState) -> anyhow::Result<SolverResult>;
 
+/// Solver registered in `SOLVERS` with the kinds of problems it handles.
+pub struct Solver {
+    pub name: &'static str,
+    pub kinds: &'static [ProblemKind],
+    pub run: SolverType,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use line_drawing::Bresenham;
+    use std::io::Cursor;
 
     #[test]
-    fn test() {
+    fn test() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA001_tgt.mdl");
+        let target = mdl::read_matrix(&mut Cursor::new(data))?;
+        let state = SolverState {
+            source: Matrix::new(target.r),
+            target,
+            max_bots: 20,
+            cancellation: Cancellation::default(),
+        };
+
         for solver in SOLVERS {
-            println!("{:?}", solver(&SolverState {}));
+            println!(
+                "{} {:?}",
+                solver.name,
+                (solver.run)(&state).map(|result| result.elapsed)
+            );
         }
+        Ok(())
     }
 
     #[test]
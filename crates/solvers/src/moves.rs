@@ -0,0 +1,91 @@
+use bot::Position;
+use commands::{Command, Difference, SMove};
+
+pub const LONG_LINEAR_MAX: i32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn difference(self, distance: i32) -> (i8, i8, i8) {
+        let d = distance as i8;
+        match self {
+            Axis::X => (d, 0, 0),
+            Axis::Y => (0, d, 0),
+            Axis::Z => (0, 0, d),
+        }
+    }
+
+    /// Index of the axis in `[x, y, z]` coordinates.
+    pub fn index(self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+
+    pub fn coordinate(self, position: &Position) -> i32 {
+        match self {
+            Axis::X => position.x as i32,
+            Axis::Y => position.y as i32,
+            Axis::Z => position.z as i32,
+        }
+    }
+
+    pub fn with_coordinate(self, position: &Position, value: i32) -> Position {
+        let mut position = position.clone();
+        match self {
+            Axis::X => position.x = value as u8,
+            Axis::Y => position.y = value as u8,
+            Axis::Z => position.z = value as u8,
+        }
+        position
+    }
+}
+
+pub fn smove(axis: Axis, distance: i32) -> Command {
+    let (dx, dy, dz) = axis.difference(distance);
+    Command::SMove(SMove {
+        lld: Difference::long_linear(dx, dy, dz),
+    })
+}
+
+/// Moves along a single axis, split into SMoves of at most 15 voxels.
+pub fn straight_moves(axis: Axis, distance: i32) -> Vec<Command> {
+    let mut commands = vec![];
+    let mut left = distance;
+
+    while left != 0 {
+        let d = left.clamp(-LONG_LINEAR_MAX, LONG_LINEAR_MAX);
+        commands.push(smove(axis, d));
+        left -= d;
+    }
+
+    commands
+}
+
+/// Moves from `from` to `to` one axis at a time in the given order. The
+/// caller is responsible for the path being free.
+pub fn moves_between(from: &Position, to: &Position, order: [Axis; 3]) -> Vec<Command> {
+    order
+        .into_iter()
+        .flat_map(|axis| straight_moves(axis, axis.coordinate(to) - axis.coordinate(from)))
+        .collect()
+}
+
+/// Appends per-bot plans (in bid order) to a flat trace, one command of
+/// every plan per step, padding plans that finished early with `Wait`.
+pub fn push_lockstep(trace: &mut Vec<Command>, plans: &[Vec<Command>]) {
+    let steps = plans.iter().map(Vec::len).max().unwrap_or_default();
+
+    for step in 0..steps {
+        for plan in plans {
+            trace.push(plan.get(step).cloned().unwrap_or(Command::Wait));
+        }
+    }
+}
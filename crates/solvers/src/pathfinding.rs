@@ -0,0 +1,296 @@
+use crate::moves::{Axis, LONG_LINEAR_MAX};
+use bot::Position;
+use commands::{Command, Difference, LMove, SMove};
+use mdl::SparseMatrix;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+pub const SHORT_LINEAR_MAX: i32 = 5;
+
+/// Weight of a single step in path costs. Every step costs at least 3·R³
+/// in the real energy model, so fewer steps always win and move energy
+/// only breaks ties, which favours long straight moves.
+const STEP_COST: u64 = 1000;
+
+const DIRECTIONS: [(Axis, i32); 6] = [
+    (Axis::X, 1),
+    (Axis::X, -1),
+    (Axis::Y, 1),
+    (Axis::Y, -1),
+    (Axis::Z, 1),
+    (Axis::Z, -1),
+];
+
+/// Lower bound of the cost of moving `distance` voxels (manhattan).
+fn cost_bound(distance: u32) -> u64 {
+    (distance as u64).div_ceil(LONG_LINEAR_MAX as u64) * STEP_COST + 2 * distance as u64
+}
+
+fn manhattan(a: &Position, b: &Position) -> u32 {
+    a.x.abs_diff(b.x) as u32 + a.y.abs_diff(b.y) as u32 + a.z.abs_diff(b.z) as u32
+}
+
+/// Cheapest SMove/LMove sequence from `from` to `to` that only sweeps
+/// through Void voxels not in `blocked`.
+pub fn find_moves(
+    matrix: &SparseMatrix,
+    blocked: &HashSet<Position>,
+    from: &Position,
+    to: &Position,
+) -> Option<Vec<Command>> {
+    search(
+        matrix,
+        blocked,
+        from,
+        |position| position == to,
+        |position| cost_bound(manhattan(position, to)),
+    )
+    .map(|(commands, _)| commands)
+}
+
+/// Cheapest SMove/LMove sequence from `from` to any position accepted by
+/// `goal`, all of which must lie within `radius` of `center`. Returns the
+/// moves and the position they end at.
+pub fn find_moves_near(
+    matrix: &SparseMatrix,
+    blocked: &HashSet<Position>,
+    from: &Position,
+    center: &Position,
+    radius: u32,
+    goal: impl Fn(&Position) -> bool,
+) -> Option<(Vec<Command>, Position)> {
+    search(matrix, blocked, from, goal, |position| {
+        cost_bound(manhattan(position, center).saturating_sub(radius))
+    })
+}
+
+/// A* over positions reachable with a single SMove or LMove. `heuristic`
+/// must never overestimate the remaining cost, `|_| 0` turns it into a plain
+/// Dijkstra search.
+pub fn search(
+    matrix: &SparseMatrix,
+    blocked: &HashSet<Position>,
+    from: &Position,
+    goal: impl Fn(&Position) -> bool,
+    heuristic: impl Fn(&Position) -> u64,
+) -> Option<(Vec<Command>, Position)> {
+    let key = |p: &Position| (p.x, p.y, p.z);
+    let mut costs: HashMap<(u8, u8, u8), u64> = HashMap::from([(key(from), 0)]);
+    let mut parents: HashMap<(u8, u8, u8), (Position, Command)> = HashMap::new();
+    let mut queue = BinaryHeap::from([Reverse((heuristic(from), 0, key(from)))]);
+
+    while let Some(Reverse((_, cost, (x, y, z)))) = queue.pop() {
+        let position = Position::new(x, y, z);
+        if cost > costs[&key(&position)] {
+            continue;
+        }
+
+        if goal(&position) {
+            let mut commands = vec![];
+            let mut current = position.clone();
+            while let Some((parent, command)) = parents.get(&key(&current)) {
+                commands.push(command.clone());
+                current = parent.clone();
+            }
+            commands.reverse();
+            return Some((commands, position));
+        }
+
+        for (next, move_cost, command) in neighbours(matrix, blocked, &position) {
+            let next_cost = cost + STEP_COST + move_cost;
+            if costs
+                .get(&key(&next))
+                .is_some_and(|known| *known <= next_cost)
+            {
+                continue;
+            }
+
+            costs.insert(key(&next), next_cost);
+            parents.insert(key(&next), (position.clone(), command));
+            queue.push(Reverse((
+                next_cost + heuristic(&next),
+                next_cost,
+                key(&next),
+            )));
+        }
+    }
+
+    None
+}
+
+/// Positions along `axis` in direction `sign` from `from`, up to `limit`
+/// voxels away, stopping before the first one that is not free. The nearest
+/// Full voxel comes from the sparse model, so rays through empty space don't
+/// test voxels one by one.
+fn ray(
+    matrix: &SparseMatrix,
+    blocked: &HashSet<Position>,
+    from: &Position,
+    (axis, sign): (Axis, i32),
+    limit: i32,
+) -> Vec<Position> {
+    let r = matrix.r() as i32;
+    let start = [from.x, from.y, from.z].map(|value| value as usize);
+    let wall = matrix
+        .next_filled(start, axis.index(), sign > 0)
+        .map_or(limit, |voxel| {
+            (voxel[axis.index()] as i32 - axis.coordinate(from)).abs() - 1
+        });
+    (1..=limit.min(wall))
+        .map(|distance| axis.coordinate(from) + sign * distance)
+        .take_while(|value| (0..r).contains(value))
+        .map(|value| axis.with_coordinate(from, value))
+        .take_while(|position| !blocked.contains(position))
+        .collect()
+}
+
+fn linear(axis: Axis, distance: i32, short: bool) -> Difference {
+    let (dx, dy, dz) = axis.difference(distance);
+    if short {
+        Difference::short_linear(dx, dy, dz)
+    } else {
+        Difference::long_linear(dx, dy, dz)
+    }
+}
+
+/// Every position a single SMove or LMove takes the bot to, with the move
+/// energy and the command itself.
+fn neighbours(
+    matrix: &SparseMatrix,
+    blocked: &HashSet<Position>,
+    from: &Position,
+) -> Vec<(Position, u64, Command)> {
+    let mut result = vec![];
+
+    for direction in DIRECTIONS {
+        let (axis, sign) = direction;
+        for (i, next) in ray(matrix, blocked, from, direction, LONG_LINEAR_MAX)
+            .into_iter()
+            .enumerate()
+        {
+            let distance = i as i32 + 1;
+            result.push((
+                next,
+                2 * distance as u64,
+                Command::SMove(SMove {
+                    lld: linear(axis, sign * distance, false),
+                }),
+            ));
+        }
+
+        for (i, corner) in ray(matrix, blocked, from, direction, SHORT_LINEAR_MAX)
+            .into_iter()
+            .enumerate()
+        {
+            let first = i as i32 + 1;
+            for second_direction in DIRECTIONS.into_iter().filter(|(a, _)| *a != axis) {
+                let (second_axis, second_sign) = second_direction;
+                for (j, next) in ray(matrix, blocked, &corner, second_direction, SHORT_LINEAR_MAX)
+                    .into_iter()
+                    .enumerate()
+                {
+                    let second = j as i32 + 1;
+                    result.push((
+                        next,
+                        2 * (first + 2 + second) as u64,
+                        Command::LMove(LMove {
+                            sld1: linear(axis, sign * first, true),
+                            sld2: linear(second_axis, second_sign * second, true),
+                        }),
+                    ));
+                }
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bot::get_position_by_diff;
+    use mdl::CellState;
+
+    fn is_free(matrix: &SparseMatrix, blocked: &HashSet<Position>, position: &Position) -> bool {
+        !blocked.contains(position)
+            && !matrix.is_filled(
+                position.x as usize,
+                position.y as usize,
+                position.z as usize,
+            )
+    }
+
+    /// Replays moves voxel by voxel, checking every swept voxel is free.
+    fn replay(
+        matrix: &SparseMatrix,
+        blocked: &HashSet<Position>,
+        from: &Position,
+        commands: &[Command],
+    ) -> Position {
+        let mut position = from.clone();
+        for command in commands {
+            let legs = match command {
+                Command::SMove(m) => vec![&m.lld],
+                Command::LMove(m) => vec![&m.sld1, &m.sld2],
+                _ => panic!("unexpected command {command:?}"),
+            };
+            for leg in legs {
+                let length = leg.dx.abs() + leg.dy.abs() + leg.dz.abs();
+                let unit = Difference::near(leg.dx.signum(), leg.dy.signum(), leg.dz.signum());
+                for _ in 0..length {
+                    position = get_position_by_diff(&position, &unit);
+                    assert!(is_free(matrix, blocked, &position), "{position:?} is taken");
+                }
+            }
+        }
+        position
+    }
+
+    #[test]
+    fn test_straight_path_uses_long_moves() {
+        let matrix = SparseMatrix::new(50);
+        let from = Position::new(0, 0, 0);
+        let to = Position::new(40, 0, 0);
+
+        let commands = find_moves(&matrix, &HashSet::new(), &from, &to).unwrap();
+
+        assert_eq!(3, commands.len());
+        assert_eq!(to, replay(&matrix, &HashSet::new(), &from, &commands));
+    }
+
+    #[test]
+    fn test_path_avoids_walls_and_bots() {
+        let mut matrix = SparseMatrix::new(12);
+        for y in 0..10 {
+            for z in 0..12 {
+                matrix.set(5, y, z, CellState::Fill);
+            }
+        }
+        let blocked = HashSet::from([Position::new(5, 10, 3)]);
+        let from = Position::new(1, 0, 3);
+        let to = Position::new(9, 0, 3);
+
+        let commands = find_moves(&matrix, &blocked, &from, &to).unwrap();
+
+        assert_eq!(to, replay(&matrix, &blocked, &from, &commands));
+    }
+
+    #[test]
+    fn test_enclosed_target_is_unreachable() {
+        let mut matrix = SparseMatrix::new(5);
+        for x in 0..5 {
+            for z in 0..5 {
+                matrix.set(x, 1, z, CellState::Fill);
+            }
+        }
+
+        let path = find_moves(
+            &matrix,
+            &HashSet::new(),
+            &Position::new(0, 0, 0),
+            &Position::new(2, 3, 2),
+        );
+        assert!(path.is_none());
+    }
+}
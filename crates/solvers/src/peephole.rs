@@ -0,0 +1,338 @@
+use crate::moves::LONG_LINEAR_MAX;
+use crate::pathfinding::SHORT_LINEAR_MAX;
+use commands::{Command, Difference, LMove, Mlen, SMove};
+use mdl::Matrix;
+use state::{volatile, Harmonic, State};
+use std::collections::HashSet;
+
+#[derive(Debug)]
+pub struct Optimized {
+    pub trace: Vec<Command>,
+    pub energy: i64,
+    /// Energy saved compared to the original trace.
+    pub saved: i64,
+}
+
+/// Splits a flat trace into steps, following the bot count through
+/// fissions and fusions.
+pub fn split_steps(trace: &[Command]) -> anyhow::Result<Vec<Vec<Command>>> {
+    let mut steps = vec![];
+    let mut commands = trace;
+    let mut count = 1;
+
+    while !commands.is_empty() {
+        if commands.len() < count {
+            return Err(anyhow::anyhow!(
+                "Trace ended in the middle of step {}",
+                steps.len()
+            ));
+        }
+
+        let (step, rest) = commands.split_at(count);
+        let fissions = step
+            .iter()
+            .filter(|command| matches!(command, Command::Fission(_)))
+            .count();
+        let fusions = step
+            .iter()
+            .filter(|command| matches!(command, Command::FusionS(_)))
+            .count();
+        count = count + fissions - fusions;
+        steps.push(step.to_vec());
+        commands = rest;
+    }
+
+    Ok(steps)
+}
+
+/// Rewrites a halting trace that starts from `source`: consecutive moves of
+/// a bot are merged into one `SMove` or `LMove` leaving a `Wait` behind,
+/// a Flip to High cancelled by a Flip in the next step is dropped, and steps
+/// where every bot waits are removed. Rewrites only happen when the merged
+/// move keeps the step free of volatile conflicts, and the result is
+/// simulated again, falling back to the original trace unless it builds the
+/// same model for less energy.
+pub fn optimize(source: &Matrix, max_bots: u8, trace: &[Command]) -> anyhow::Result<Optimized> {
+    let original = simulate(source, max_bots, trace)?;
+    let mut steps = split_steps(trace)?;
+
+    loop {
+        let rewritten = rewrite(source, max_bots, &mut steps)?;
+        let dropped = drop_idle_steps(&mut steps);
+        if !rewritten && !dropped {
+            break;
+        }
+    }
+
+    let optimized = steps.concat();
+    match simulate(source, max_bots, &optimized) {
+        Ok(state) if state.matrix == original.matrix && state.energy < original.energy => {
+            Ok(Optimized {
+                trace: optimized,
+                energy: state.energy,
+                saved: original.energy - state.energy,
+            })
+        }
+        _ => Ok(Optimized {
+            trace: trace.to_vec(),
+            energy: original.energy,
+            saved: 0,
+        }),
+    }
+}
+
+fn simulate(source: &Matrix, max_bots: u8, trace: &[Command]) -> anyhow::Result<State> {
+    let mut state = State::new(max_bots, source.clone());
+    state.execute(trace)?;
+    if !state.halted {
+        return Err(anyhow::anyhow!("Trace doesn't halt"));
+    }
+    Ok(state)
+}
+
+/// Removes the steps where every bot waits, returning whether there were
+/// any.
+fn drop_idle_steps(steps: &mut Vec<Vec<Command>>) -> bool {
+    let count = steps.len();
+    steps.retain(|step| step.iter().any(|command| *command != Command::Wait));
+    steps.len() < count
+}
+
+/// Runs one pass of rewrites over the steps, returning whether anything
+/// changed.
+fn rewrite(source: &Matrix, max_bots: u8, steps: &mut [Vec<Command>]) -> anyhow::Result<bool> {
+    let mut state = State::new(max_bots, source.clone());
+    let mut changed = false;
+
+    for t in 0..steps.len() {
+        let (current, rest) = steps.split_at_mut(t + 1);
+        let step = &mut current[t];
+        let next = rest.first_mut();
+
+        if let Some(next) = next {
+            // Bots keep their places in the next step unless some fission or
+            // fusion happens in this one.
+            let stable = !step.iter().any(|command| {
+                matches!(
+                    command,
+                    Command::Fission(_) | Command::FusionP(_) | Command::FusionS(_)
+                )
+            });
+
+            let mut lmoves = vec![];
+            for i in (0..step.len()).filter(|_| stable) {
+                let Some(merged) = merge(&step[i], &next[i]) else {
+                    continue;
+                };
+
+                let is_lmove = matches!(merged, Command::LMove(_));
+                let original = std::mem::replace(&mut step[i], merged);
+                if is_valid(&state, step) {
+                    let second = std::mem::replace(&mut next[i], Command::Wait);
+                    if is_lmove {
+                        lmoves.push((i, original, second));
+                    } else {
+                        changed = true;
+                    }
+                } else {
+                    step[i] = original;
+                }
+            }
+
+            // An LMove costs 4 more than its two SMoves, which only pays off
+            // when it empties the next step so the step can be dropped.
+            if next.iter().all(|command| *command == Command::Wait) {
+                changed |= !lmoves.is_empty();
+            } else {
+                for (i, first, second) in lmoves {
+                    step[i] = first;
+                    next[i] = second;
+                }
+            }
+
+            let flips = step.iter().position(|command| *command == Command::Flip);
+            let cancelled = next.iter().position(|command| *command == Command::Flip);
+            let was_low = state.harmonic == Harmonic::Low;
+            state.step(step)?;
+
+            if let (Some(flip), Some(cancel)) = (flips, cancelled) {
+                // The flip only matters for the harmonic at the end of this
+                // step, which may as well stay Low when the model is grounded.
+                if was_low && state.grounded {
+                    step[flip] = Command::Wait;
+                    next[cancel] = Command::Wait;
+                    state.harmonic = Harmonic::Low;
+                    changed = true;
+                }
+            }
+        } else {
+            state.step(step)?;
+        }
+
+        if state.halted {
+            break;
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Single move equivalent to a bot running `first` then `second`.
+fn merge(first: &Command, second: &Command) -> Option<Command> {
+    let (Command::SMove(a), Command::SMove(b)) = (first, second) else {
+        return None;
+    };
+    let (a, b) = (&a.lld, &b.lld);
+    let axis = |d: &Difference| (d.dx != 0, d.dy != 0, d.dz != 0);
+
+    if axis(a) == axis(b) {
+        let sum = Difference::long_linear(a.dx + b.dx, a.dy + b.dy, a.dz + b.dz);
+        return match sum.mlen() as i32 {
+            0 => Some(Command::Wait),
+            length if length <= LONG_LINEAR_MAX => Some(Command::SMove(SMove { lld: sum })),
+            _ => None,
+        };
+    }
+
+    let short = SHORT_LINEAR_MAX as u32;
+    (a.mlen() <= short && b.mlen() <= short).then(|| {
+        Command::LMove(LMove {
+            sld1: Difference::short_linear(a.dx, a.dy, a.dz),
+            sld2: Difference::short_linear(b.dx, b.dy, b.dz),
+        })
+    })
+}
+
+/// Checks that the volatile cells of a step don't overlap and that moves
+/// only sweep Void cells.
+fn is_valid(state: &State, step: &[Command]) -> bool {
+    let mut taken = HashSet::new();
+
+    for (bid, command) in state.active_bids().into_iter().zip(step) {
+        let Some(bot) = state.bot(bid) else {
+            return false;
+        };
+        // Group regions are shared, leave those steps alone.
+        if matches!(command, Command::GFill(_) | Command::GVoid(_)) {
+            return false;
+        }
+        let Ok(cells) = volatile(bot.position(), command) else {
+            return false;
+        };
+
+        let moving = matches!(command, Command::SMove(_) | Command::LMove(_));
+        for cell in cells {
+            let full = state
+                .matrix
+                .is_filled(cell.x as usize, cell.y as usize, cell.z as usize);
+            if (moving && full) || !taken.insert(cell) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::{smove, Axis};
+    use commands::{Fill, Fission, FusionP, FusionS, Void};
+
+    #[test]
+    fn test_optimize_drops_idle_steps() -> anyhow::Result<()> {
+        let trace = vec![Command::Wait, Command::Wait, Command::Halt];
+
+        let optimized = optimize(&Matrix::new(5), 1, &trace)?;
+
+        assert_eq!(vec![Command::Halt], optimized.trace);
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimize_merges_lmove_only_when_step_empties() -> anyhow::Result<()> {
+        let void = Command::Void(Void {
+            nd: Difference::near(0, -1, 0),
+        });
+        let fission = Command::Fission(Fission {
+            nd: Difference::near(1, 0, 0),
+            m: 0,
+        });
+        let fusion = [
+            Command::FusionP(FusionP {
+                nd: Difference::near(1, 0, 0),
+            }),
+            Command::FusionS(FusionS {
+                nd: Difference::near(-1, 0, 0),
+            }),
+        ];
+        let trace = [
+            vec![fission.clone()],
+            vec![smove(Axis::Y, 1), smove(Axis::Y, 1)],
+            vec![smove(Axis::Z, 1), void.clone()],
+            vec![smove(Axis::Z, -1), Command::Wait],
+            vec![smove(Axis::Y, -1), smove(Axis::Y, -1)],
+            fusion.to_vec(),
+            vec![Command::Halt],
+        ]
+        .concat();
+
+        let optimized = optimize(&Matrix::new(5), 2, &trace)?;
+
+        // Bot 2 voids while bot 1 would turn, so bot 1 keeps its SMoves,
+        // which then cancel out.
+        assert_eq!(
+            [
+                vec![fission],
+                vec![smove(Axis::Y, 1), smove(Axis::Y, 1)],
+                vec![Command::Wait, void],
+                vec![smove(Axis::Y, -1), smove(Axis::Y, -1)],
+                fusion.to_vec(),
+                vec![Command::Halt],
+            ]
+            .concat(),
+            optimized.trace
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimize_merges_moves_and_drops_waits() -> anyhow::Result<()> {
+        let fill = Command::Fill(Fill {
+            nd: Difference::near(0, -1, 1),
+        });
+        let trace = vec![
+            Command::Flip,
+            Command::Flip,
+            smove(Axis::X, 2),
+            smove(Axis::X, 3),
+            smove(Axis::Y, 1),
+            Command::Wait,
+            fill.clone(),
+            smove(Axis::Y, -1),
+            smove(Axis::X, -5),
+            Command::Halt,
+        ];
+
+        let optimized = optimize(&Matrix::new(10), 1, &trace)?;
+
+        assert_eq!(
+            vec![
+                Command::LMove(LMove {
+                    sld1: Difference::short_linear(5, 0, 0),
+                    sld2: Difference::short_linear(0, 1, 0),
+                }),
+                fill,
+                Command::LMove(LMove {
+                    sld1: Difference::short_linear(0, -1, 0),
+                    sld2: Difference::short_linear(-5, 0, 0),
+                }),
+                Command::Halt,
+            ],
+            optimized.trace
+        );
+        assert!(optimized.saved > 0);
+        Ok(())
+    }
+}
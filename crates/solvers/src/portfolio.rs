@@ -0,0 +1,268 @@
+use crate::peephole::optimize;
+use crate::{Solver, SolverResult, SolverState, SOLVERS};
+use commands::Command;
+use log::{info, warn};
+use state::State;
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Trace checked with the simulator.
+#[derive(Debug)]
+pub struct Verified {
+    pub solver: &'static str,
+    pub trace: Vec<Command>,
+    pub energy: i64,
+    pub elapsed: Duration,
+}
+
+/// Time solvers get after their deadline to return the best trace so far.
+const GRACE: Duration = Duration::from_secs(1);
+
+/// Runs every solver registered for the kind of `problem` on its own
+/// thread, runs the peephole optimizer over each trace while there's time
+/// left, verifies it with the simulator and returns the valid one with the
+/// lowest energy. Solvers are cancelled after `budget` and
+/// given up on if they don't return shortly after, failures are logged.
+pub fn run_portfolio(problem: &SolverState, budget: Duration) -> anyhow::Result<Verified> {
+    let kind = problem.kind();
+    let solvers = SOLVERS.iter().filter(|solver| solver.kinds.contains(&kind));
+    run_solvers(problem, budget, solvers)
+}
+
+fn run_solvers(
+    problem: &SolverState,
+    budget: Duration,
+    solvers: impl Iterator<Item = &'static Solver>,
+) -> anyhow::Result<Verified> {
+    let kind = problem.kind();
+    let deadline = Instant::now() + budget;
+    let mut problem = problem.clone();
+    problem.cancellation = problem.cancellation.with_deadline(deadline);
+    let problem = Arc::new(problem);
+    let (sender, receiver) = mpsc::channel();
+    let mut pending = HashSet::new();
+
+    for solver in solvers {
+        let sender = sender.clone();
+        let problem = problem.clone();
+        pending.insert(solver.name);
+
+        thread::spawn(move || {
+            let verified = (solver.run)(&problem)
+                .map(|result| optimized(&problem, solver.name, result))
+                .and_then(|result| verify(&problem, solver.name, result));
+            // The receiver is gone once the budget ran out.
+            let _ = sender.send((solver.name, verified));
+        });
+    }
+    drop(sender);
+
+    let mut best: Option<Verified> = None;
+
+    while !pending.is_empty() {
+        let left = (deadline + GRACE).saturating_duration_since(Instant::now());
+        let Ok((name, verified)) = receiver.recv_timeout(left) else {
+            for name in &pending {
+                warn!("{name} didn't finish within {budget:?}");
+            }
+            break;
+        };
+        pending.remove(name);
+
+        match verified {
+            Ok(verified) => {
+                info!(
+                    "{name} spent {} energy in {:?}",
+                    verified.energy, verified.elapsed
+                );
+                if best
+                    .as_ref()
+                    .is_none_or(|best| verified.energy < best.energy)
+                {
+                    best = Some(verified);
+                }
+            }
+            Err(e) => warn!("{name} failed: {e}"),
+        }
+    }
+
+    best.ok_or_else(|| anyhow::anyhow!("No solver produced a valid trace for {kind:?}"))
+}
+
+/// Runs the peephole optimizer over a solver's trace, keeping it as is once
+/// the deadline passed or when it doesn't simulate.
+fn optimized(problem: &SolverState, solver: &'static str, result: SolverResult) -> SolverResult {
+    if problem.cancellation.is_cancelled() {
+        return result;
+    }
+
+    match optimize(&problem.source, problem.max_bots, &result.trace) {
+        Ok(optimized) => {
+            info!("{solver} trace optimized, {} energy saved", optimized.saved);
+            SolverResult {
+                elapsed: result.elapsed,
+                trace: optimized.trace,
+            }
+        }
+        Err(_) => result,
+    }
+}
+
+/// Simulates a solver's trace, checking it halts with the target model.
+pub fn verify(
+    problem: &SolverState,
+    solver: &'static str,
+    result: SolverResult,
+) -> anyhow::Result<Verified> {
+    let mut state = State::new(problem.max_bots, problem.source.clone());
+    state.execute(&result.trace)?;
+    state.check_target(&problem.target)?;
+
+    Ok(Verified {
+        solver,
+        trace: result.trace,
+        energy: state.energy,
+        elapsed: result.elapsed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strip_solver::strip_trace;
+    use crate::{Cancellation, ProblemKind};
+    use mdl::Matrix;
+    use std::io::Cursor;
+
+    fn problem() -> anyhow::Result<SolverState> {
+        let data = include_bytes!("../../../data/FA001_tgt.mdl");
+        let target = mdl::read_matrix(&mut Cursor::new(data))?;
+        Ok(SolverState {
+            source: Matrix::new(target.r),
+            target,
+            max_bots: 20,
+            cancellation: Cancellation::default(),
+        })
+    }
+
+    /// Keeps a valid trace and polls for the deadline, as solvers improving
+    /// on their best trace do.
+    fn anytime(state: &SolverState) -> anyhow::Result<SolverResult> {
+        let start = Instant::now();
+        let trace = strip_trace(&state.target, 1)?;
+        while !state.cancellation.is_cancelled() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        Ok(SolverResult {
+            elapsed: start.elapsed(),
+            trace,
+        })
+    }
+
+    /// Ignores cancellation and returns long after the grace period.
+    fn stuck(state: &SolverState) -> anyhow::Result<SolverResult> {
+        thread::sleep(GRACE * 5);
+        anytime(state)
+    }
+
+    fn strip(state: &SolverState) -> anyhow::Result<SolverResult> {
+        let start = Instant::now();
+        let trace = strip_trace(&state.target, state.max_bots)?;
+        Ok(SolverResult {
+            elapsed: start.elapsed(),
+            trace,
+        })
+    }
+
+    static ANYTIME: Solver = Solver {
+        name: "anytime",
+        kinds: &[ProblemKind::Assembly],
+        run: anytime,
+    };
+
+    static STUCK: Solver = Solver {
+        name: "stuck",
+        kinds: &[ProblemKind::Assembly],
+        run: stuck,
+    };
+
+    static STRIP: Solver = Solver {
+        name: "strip",
+        kinds: &[ProblemKind::Assembly],
+        run: strip,
+    };
+
+    #[test]
+    fn test_portfolio_returns_best_trace_at_deadline() -> anyhow::Result<()> {
+        let problem = problem()?;
+        let budget = Duration::from_millis(200);
+        let start = Instant::now();
+
+        let best = run_solvers(&problem, budget, [&ANYTIME].into_iter())?;
+
+        assert!(start.elapsed() >= budget);
+        assert!(start.elapsed() < budget + GRACE);
+        assert_eq!("anytime", best.solver);
+        Ok(())
+    }
+
+    #[test]
+    fn test_portfolio_abandons_solver_after_grace() -> anyhow::Result<()> {
+        let problem = problem()?;
+        let budget = Duration::from_millis(200);
+        let start = Instant::now();
+
+        let best = run_solvers(&problem, budget, [&STUCK, &STRIP].into_iter())?;
+
+        assert!(start.elapsed() < budget + GRACE * 2);
+        assert_eq!("strip", best.solver);
+
+        let start = Instant::now();
+        assert!(run_solvers(&problem, budget, [&STUCK].into_iter()).is_err());
+        assert!(start.elapsed() < budget + GRACE * 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_portfolio_keeps_valid_trace() -> anyhow::Result<()> {
+        let problem = problem()?;
+
+        let best = run_portfolio(&problem, Duration::from_secs(60))?;
+
+        let verified = verify(
+            &problem,
+            best.solver,
+            SolverResult {
+                elapsed: best.elapsed,
+                trace: best.trace,
+            },
+        )?;
+        assert_eq!(best.energy, verified.energy);
+        Ok(())
+    }
+
+    #[test]
+    fn test_portfolio_returns_trace_once_cancelled() -> anyhow::Result<()> {
+        let problem = problem()?;
+
+        problem.cancellation.cancel();
+        let budget = Duration::from_secs(60);
+        let start = Instant::now();
+        let best = run_portfolio(&problem, budget)?;
+
+        assert!(start.elapsed() < budget);
+        let verified = verify(
+            &problem,
+            best.solver,
+            SolverResult {
+                elapsed: best.elapsed,
+                trace: best.trace,
+            },
+        )?;
+        assert_eq!(best.energy, verified.energy);
+        Ok(())
+    }
+}
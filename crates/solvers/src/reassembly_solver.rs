@@ -0,0 +1,244 @@
+use crate::crew::{crew_size, Crew};
+use crate::gfill_solver::{decompose, gfill_trace, order_cuboids, MAX_CUBOID_SIDE};
+use crate::gvoid_solver::gvoid_trace;
+use crate::harmonics::minimize_harmonics;
+use crate::strip_solver::{strip_trace, unstrip_trace};
+use crate::{Cancellation, ProblemKind, Solver, SolverResult, SolverState, SOLVERS};
+use commands::Command;
+use linkme::distributed_slice;
+use mdl::{CellState, Matrix};
+use state::State;
+use std::time::Instant;
+
+#[distributed_slice(SOLVERS)]
+static REASSEMBLY_SOLVER: Solver = Solver {
+    name: "reassembly",
+    kinds: &[ProblemKind::Reassembly],
+    run: reassembly_solver,
+};
+
+fn reassembly_solver(state: &SolverState) -> anyhow::Result<SolverResult> {
+    let start = Instant::now();
+    state.expect_kind(ProblemKind::Reassembly)?;
+    let trace = reassembly_trace(
+        &state.source,
+        &state.target,
+        state.max_bots,
+        &state.cancellation,
+    )?;
+    Ok(SolverResult {
+        elapsed: start.elapsed(),
+        trace,
+    })
+}
+
+/// Turns `source` into `target` by either touching only the voxels that
+/// differ, or disassembling everything and assembling from scratch. All
+/// candidates are simulated and the cheapest valid trace is returned, once
+/// cancelled the best one so far, or the strip disassembly and assembly
+/// when no candidate finished.
+pub fn reassembly_trace(
+    source: &Matrix,
+    target: &Matrix,
+    max_bots: u8,
+    cancellation: &Cancellation,
+) -> anyhow::Result<Vec<Command>> {
+    type Candidate<'a> = Box<dyn Fn() -> anyhow::Result<Vec<Command>> + 'a>;
+    let candidates: [(&str, Candidate); 3] = [
+        (
+            "diff",
+            Box::new(|| diff_trace(source, target, max_bots, cancellation)),
+        ),
+        (
+            "gvoid+gfill",
+            Box::new(|| {
+                chain(
+                    gvoid_trace(source, max_bots, cancellation),
+                    gfill_trace(target, max_bots, cancellation),
+                )
+            }),
+        ),
+        (
+            "gvoid+strip",
+            Box::new(|| {
+                chain(
+                    gvoid_trace(source, max_bots, cancellation),
+                    strip_trace(target, max_bots),
+                )
+            }),
+        ),
+    ];
+
+    let mut best: Option<(i64, Vec<Command>)> = None;
+    let mut errors = vec![];
+
+    for (name, candidate) in candidates {
+        if best.is_some() && cancellation.is_cancelled() {
+            break;
+        }
+
+        let simulated = candidate()
+            .and_then(|trace| minimize_harmonics(source, max_bots, &trace))
+            .and_then(|trace| {
+                simulate(source, target, max_bots, &trace).map(|energy| (energy, trace))
+            });
+
+        match simulated {
+            Ok((energy, trace)) => {
+                if best.as_ref().is_none_or(|(best, _)| energy < *best) {
+                    best = Some((energy, trace));
+                }
+            }
+            Err(e) => errors.push(format!("{name}: {e}")),
+        }
+    }
+
+    if best.is_none() && cancellation.is_cancelled() {
+        return chain(
+            unstrip_trace(source, max_bots),
+            strip_trace(target, max_bots),
+        );
+    }
+
+    best.map(|(_, trace)| trace)
+        .ok_or_else(|| anyhow::anyhow!("No valid reassembly trace, {}", errors.join("; ")))
+}
+
+/// Voids `source \ target` top first, keeping the shared voxels, then fills
+/// `target \ source`.
+fn diff_trace(
+    source: &Matrix,
+    target: &Matrix,
+    max_bots: u8,
+    cancellation: &Cancellation,
+) -> anyhow::Result<Vec<Command>> {
+    let r = source.r;
+    let mut kept = Matrix::new(r);
+    let mut removed = Matrix::new(r);
+    let mut added = Matrix::new(r);
+
+    for cell in source.iter() {
+        let wanted = target.is_filled(cell.x, cell.y, cell.z);
+        let part = match (&cell.state, wanted) {
+            (CellState::Fill, true) => &mut kept,
+            (CellState::Fill, false) => &mut removed,
+            (CellState::Void, true) => &mut added,
+            (CellState::Void, false) => continue,
+        };
+        part.set(cell.x, cell.y, cell.z, CellState::Fill);
+    }
+
+    let (mut removals, removals_grounded) =
+        order_cuboids(decompose(&removed, MAX_CUBOID_SIDE), kept.clone());
+    removals.reverse();
+    let (additions, additions_grounded) =
+        order_cuboids(decompose(&added, MAX_CUBOID_SIDE), kept.clone());
+    let grounded = kept.is_grounded() && removals_grounded && additions_grounded;
+
+    if removals.is_empty() && additions.is_empty() {
+        return Ok(vec![Command::Halt]);
+    }
+
+    let cuboids = [removals.as_slice(), additions.as_slice()].concat();
+    let mut crew = Crew::spawn(source.clone(), crew_size(&cuboids, max_bots, r)?);
+    if !grounded {
+        crew.trace.insert(0, Command::Flip);
+    }
+
+    for cuboid in &removals {
+        cancellation.check()?;
+        crew.set_region(cuboid, CellState::Void)?;
+    }
+    for cuboid in &additions {
+        cancellation.check()?;
+        crew.set_region(cuboid, CellState::Fill)?;
+    }
+
+    crew.gather()?;
+    if !grounded {
+        crew.trace.push(Command::Flip);
+    }
+    crew.trace.push(Command::Halt);
+
+    Ok(crew.trace)
+}
+
+/// Runs `disassembly` up to its `Halt` and continues with `assembly`.
+fn chain(
+    disassembly: anyhow::Result<Vec<Command>>,
+    assembly: anyhow::Result<Vec<Command>>,
+) -> anyhow::Result<Vec<Command>> {
+    let mut trace = disassembly?;
+    if trace.pop() != Some(Command::Halt) {
+        return Err(anyhow::anyhow!("Disassembly trace doesn't end with Halt"));
+    }
+
+    trace.extend(assembly?);
+    Ok(trace)
+}
+
+fn simulate(
+    source: &Matrix,
+    target: &Matrix,
+    max_bots: u8,
+    trace: &[Command],
+) -> anyhow::Result<i64> {
+    let mut state = State::new(max_bots, source.clone());
+    state.execute(trace)?;
+    state.check_target(target)?;
+
+    Ok(state.energy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_reassembly_reaches_target() -> anyhow::Result<()> {
+        let source = include_bytes!("../../../data/FA001_tgt.mdl");
+        let source = mdl::read_matrix(&mut Cursor::new(source))?;
+        let target = include_bytes!("../../../data/FA002_tgt.mdl");
+        let target = mdl::read_matrix(&mut Cursor::new(target))?;
+
+        let trace = reassembly_trace(&source, &target, 20, &Cancellation::default())?;
+        simulate(&source, &target, 20, &trace)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_reassembly_falls_back_on_strips() -> anyhow::Result<()> {
+        let source = include_bytes!("../../../data/FA001_tgt.mdl");
+        let source = mdl::read_matrix(&mut Cursor::new(source))?;
+        let target = include_bytes!("../../../data/FA002_tgt.mdl");
+        let target = mdl::read_matrix(&mut Cursor::new(target))?;
+
+        let cancellation = Cancellation::default();
+        cancellation.cancel();
+        let trace = reassembly_trace(&source, &target, 20, &cancellation)?;
+        simulate(&source, &target, 20, &trace)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_reassembly_keeps_shared_voxels() -> anyhow::Result<()> {
+        let mut source = Matrix::new(20);
+        for x in 4..10 {
+            for z in 4..10 {
+                source.set(x, 0, z, CellState::Fill);
+            }
+        }
+        let mut target = source.clone();
+        for y in 1..6 {
+            target.set(5, y, 5, CellState::Fill);
+        }
+
+        let trace = reassembly_trace(&source, &target, 20, &Cancellation::default())?;
+        simulate(&source, &target, 20, &trace)?;
+        assert!(!trace
+            .iter()
+            .any(|command| matches!(command, Command::GVoid(_) | Command::Void(_))));
+        Ok(())
+    }
+}
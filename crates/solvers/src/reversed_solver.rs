@@ -0,0 +1,93 @@
+use crate::harmonics::minimize_harmonics;
+use crate::{ProblemKind, Solver, SolverResult, SolverState, SOLVERS};
+use commands::Command;
+use linkme::distributed_slice;
+use mdl::Matrix;
+use state::{reverse_trace, State};
+use std::time::Instant;
+
+#[distributed_slice(SOLVERS)]
+static REVERSED_SOLVER: Solver = Solver {
+    name: "reversed",
+    kinds: &[ProblemKind::Disassembly],
+    run: reversed_solver,
+};
+
+/// Solves disassembly problems by running every assembly solver on the
+/// source model and reversing its trace, keeping the cheapest one. Once
+/// cancelled, the best trace so far is returned.
+fn reversed_solver(state: &SolverState) -> anyhow::Result<SolverResult> {
+    let start = Instant::now();
+    state.expect_kind(ProblemKind::Disassembly)?;
+
+    let r = state.source.r;
+    let assembly = SolverState {
+        source: Matrix::new(r),
+        target: state.source.clone(),
+        max_bots: state.max_bots,
+        cancellation: state.cancellation.clone(),
+    };
+
+    let mut best: Option<(i64, Vec<Command>)> = None;
+    for solver in SOLVERS
+        .iter()
+        .filter(|solver| solver.kinds.contains(&ProblemKind::Assembly))
+    {
+        if best.is_some() && state.cancellation.is_cancelled() {
+            break;
+        }
+
+        let Ok(result) = (solver.run)(&assembly) else {
+            continue;
+        };
+        let Ok(trace) = reverse_trace(&assembly.source, state.max_bots, &result.trace) else {
+            continue;
+        };
+        // The reversed trace is already valid, keep it when flips can't move.
+        let trace = minimize_harmonics(&state.source, state.max_bots, &trace).unwrap_or(trace);
+
+        let mut simulation = State::new(state.max_bots, state.source.clone());
+        if simulation.execute(&trace).is_err() {
+            continue;
+        }
+        if best
+            .as_ref()
+            .is_none_or(|(energy, _)| simulation.energy < *energy)
+        {
+            best = Some((simulation.energy, trace));
+        }
+    }
+
+    let (_, trace) = best.ok_or_else(|| anyhow::anyhow!("No assembly trace to reverse"))?;
+    Ok(SolverResult {
+        elapsed: start.elapsed(),
+        trace,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cancellation;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_reversed_solver_clears_model() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA002_tgt.mdl");
+        let source = mdl::read_matrix(&mut Cursor::new(data))?;
+        let state = SolverState {
+            target: Matrix::new(source.r),
+            source: source.clone(),
+            max_bots: 20,
+            cancellation: Cancellation::default(),
+        };
+
+        let result = reversed_solver(&state)?;
+        let mut simulation = State::new(20, source);
+        simulation.execute(&result.trace)?;
+
+        assert!(simulation.halted);
+        assert!(simulation.matrix.is_empty());
+        Ok(())
+    }
+}
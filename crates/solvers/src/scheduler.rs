@@ -0,0 +1,301 @@
+use bot::Position;
+use commands::Command;
+use state::volatile;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Cells claimed by bots over future steps. `steps` holds the cells each
+/// bot runs through at a given step, `parked` the cells bots hold from a
+/// step on, either because they're done or haven't committed a path yet.
+#[derive(Debug, Default)]
+struct ReservationTable {
+    steps: BTreeMap<usize, HashMap<Position, usize>>,
+    parked: HashMap<Position, (usize, usize)>,
+}
+
+impl ReservationTable {
+    fn is_free(&self, step: usize, cells: &[Position], bot: usize) -> bool {
+        let reserved = self.steps.get(&step);
+        cells.iter().all(|cell| {
+            let taken = reserved.and_then(|cells| cells.get(cell));
+            let parked = self.parked.get(cell);
+            taken.is_none_or(|owner| *owner == bot)
+                && parked.is_none_or(|(from, owner)| *owner == bot || *from > step)
+        })
+    }
+
+    /// Whether `cell` stays free for `bot` at `step` and every step after.
+    fn is_free_from(&self, step: usize, cell: &Position, bot: usize) -> bool {
+        self.parked.get(cell).is_none_or(|(_, owner)| *owner == bot)
+            && self
+                .steps
+                .range(step..)
+                .all(|(_, cells)| cells.get(cell).is_none_or(|owner| *owner == bot))
+    }
+
+    /// Last step at which the table changes, nothing frees up after it.
+    fn horizon(&self) -> usize {
+        let last = self.steps.keys().next_back().copied();
+        let parked = self.parked.values().map(|(from, _)| *from).max();
+        last.max(parked).unwrap_or_default()
+    }
+
+    fn reserve(&mut self, step: usize, cells: Vec<Position>, bot: usize) {
+        let reserved = self.steps.entry(step).or_default();
+        for cell in cells {
+            reserved.insert(cell, bot);
+        }
+    }
+
+    fn park(&mut self, step: usize, cell: Position, bot: usize) {
+        self.parked.insert(cell, (step, bot));
+    }
+
+    fn unpark(&mut self, cell: &Position) {
+        self.parked.remove(cell);
+    }
+
+    /// Drops reservations of steps before `step`, which already ran.
+    fn prune(&mut self, step: usize) {
+        self.steps = self.steps.split_off(&step);
+    }
+}
+
+/// A plan placed in time: the cells it claims at each step, the commands
+/// to run waits included, and where the bot ends.
+struct Fitted {
+    claims: Vec<(usize, Vec<Position>)>,
+    timeline: Vec<Command>,
+    end: Position,
+}
+
+/// Fits the whole `plan` of `bot`, starting at `position` on `step`, into
+/// `table`, delaying each command to the first step its volatile cells are
+/// free while the bot waits where it is. The bot parks at its final
+/// position, which must stay free for good. Returns `None` when the bot can't wait long enough or its path is
+/// held for good by another bot, so the plan has to be retried later.
+fn fit(
+    table: &ReservationTable,
+    bot: usize,
+    mut position: Position,
+    step: usize,
+    plan: &[Command],
+) -> anyhow::Result<Option<Fitted>> {
+    let mut claims = vec![];
+    let mut timeline = vec![];
+    let mut current = step;
+    let horizon = table.horizon();
+
+    for (index, command) in plan.iter().enumerate() {
+        let cells = volatile(&position, command)?;
+        let end = match command {
+            Command::SMove(_) | Command::LMove(_) => cells.last().unwrap().clone(),
+            _ => position.clone(),
+        };
+        let last = index + 1 == plan.len();
+
+        let mut start = current;
+        while !(table.is_free(start, &cells, bot)
+            && (!last || table.is_free_from(start + 1, &end, bot)))
+        {
+            if !table.is_free(start, std::slice::from_ref(&position), bot) || start > horizon {
+                return Ok(None);
+            }
+            claims.push((start, vec![position.clone()]));
+            timeline.push(Command::Wait);
+            start += 1;
+        }
+
+        claims.push((start, cells));
+        timeline.push(command.clone());
+        position = end;
+        current = start + 1;
+    }
+
+    Ok(Some(Fitted {
+        claims,
+        timeline,
+        end: position,
+    }))
+}
+
+/// Merges independent per-bot plans (in bid order, bots starting at
+/// `positions`) into a flat trace. Bots hold their cell until they commit
+/// their whole remaining plan to a space-time reservation table, delaying
+/// commands to free steps, and hold their final cell once done. Lower bids
+/// commit first; a bot whose path crosses a bot that hasn't committed yet
+/// waits until it has.
+///
+/// Only commands a bot can run on its own are accepted, coordinated ones
+/// (fissions, fusions, group fills, halt) have to be placed in lockstep by
+/// the caller between scheduled phases. Plans are assumed to move through
+/// Void cells only.
+pub fn schedule(positions: &[Position], plans: &[Vec<Command>]) -> anyhow::Result<Vec<Command>> {
+    if positions.len() != plans.len() {
+        return Err(anyhow::anyhow!(
+            "{} plans given for {} bots",
+            plans.len(),
+            positions.len()
+        ));
+    }
+    if let Some(command) = plans.iter().flatten().find(|c| !is_independent(c)) {
+        return Err(anyhow::anyhow!(
+            "{command:?} can't be scheduled independently"
+        ));
+    }
+
+    let mut table = ReservationTable::default();
+    for (bot, position) in positions.iter().enumerate() {
+        table.park(0, position.clone(), bot);
+    }
+
+    let mut queues: Vec<Option<VecDeque<Command>>> = vec![None; plans.len()];
+    let mut trace = vec![];
+    let mut step = 0;
+
+    loop {
+        table.prune(step);
+
+        for bot in 0..plans.len() {
+            if queues[bot].is_some() || plans[bot].is_empty() {
+                continue;
+            }
+            table.unpark(&positions[bot]);
+            match fit(&table, bot, positions[bot].clone(), step, &plans[bot])? {
+                Some(fitted) => {
+                    for (at, cells) in fitted.claims {
+                        table.reserve(at, cells, bot);
+                    }
+                    table.park(step + fitted.timeline.len(), fitted.end, bot);
+                    queues[bot] = Some(fitted.timeline.into());
+                }
+                None => table.park(step, positions[bot].clone(), bot),
+            }
+        }
+
+        let running = queues.iter().flatten().any(|queue| !queue.is_empty());
+        let waiting = (0..plans.len()).any(|bot| queues[bot].is_none() && !plans[bot].is_empty());
+        if !running {
+            if waiting {
+                return Err(anyhow::anyhow!("Plans deadlock at step {step}"));
+            }
+            break;
+        }
+
+        for queue in &mut queues {
+            let command = queue.as_mut().and_then(VecDeque::pop_front);
+            trace.push(command.unwrap_or(Command::Wait));
+        }
+        step += 1;
+    }
+
+    Ok(trace)
+}
+
+fn is_independent(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::Wait
+            | Command::Flip
+            | Command::SMove(_)
+            | Command::LMove(_)
+            | Command::Fill(_)
+            | Command::Void(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::{smove, Axis};
+
+    #[test]
+    fn test_schedule_delays_crossing_bot() -> anyhow::Result<()> {
+        let positions = [Position::new(0, 0, 0), Position::new(2, 1, 0)];
+        let plans = [
+            vec![smove(Axis::X, 4)],
+            vec![smove(Axis::Y, -1), smove(Axis::Z, 3)],
+        ];
+
+        let trace = schedule(&positions, &plans)?;
+
+        assert_eq!(
+            vec![
+                smove(Axis::X, 4),
+                Command::Wait,
+                Command::Wait,
+                smove(Axis::Y, -1),
+                Command::Wait,
+                smove(Axis::Z, 3),
+            ],
+            trace
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_schedule_keeps_path_clear_ahead() -> anyhow::Result<()> {
+        // Bot 2 would park in the row bot 1 crosses on its second step.
+        let positions = [Position::new(0, 0, 0), Position::new(2, 1, 0)];
+        let plans = [
+            vec![Command::Wait, smove(Axis::X, 3)],
+            vec![smove(Axis::Y, -1)],
+        ];
+
+        let trace = schedule(&positions, &plans)?;
+
+        assert_eq!(
+            vec![
+                Command::Wait,
+                Command::Wait,
+                smove(Axis::X, 3),
+                Command::Wait,
+                Command::Wait,
+                smove(Axis::Y, -1),
+            ],
+            trace
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_schedule_waits_for_uncommitted_bot() -> anyhow::Result<()> {
+        // Bot 1 crosses bot 2's start, bot 2 can only leave once bot 1 is
+        // parked past it.
+        let positions = [Position::new(0, 0, 0), Position::new(2, 0, 0)];
+        let plans = [vec![smove(Axis::X, 4)], vec![smove(Axis::Y, 2)]];
+
+        let trace = schedule(&positions, &plans)?;
+
+        assert_eq!(
+            vec![
+                Command::Wait,
+                smove(Axis::Y, 2),
+                smove(Axis::X, 4),
+                Command::Wait,
+            ],
+            trace
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_prunes_past_steps() {
+        let mut table = ReservationTable::default();
+        table.reserve(0, vec![Position::new(1, 0, 0)], 0);
+        table.reserve(3, vec![Position::new(2, 0, 0)], 0);
+
+        table.prune(2);
+
+        assert_eq!(vec![3], table.steps.keys().copied().collect::<Vec<_>>());
+        assert!(table.is_free(0, &[Position::new(1, 0, 0)], 1));
+        assert!(!table.is_free(3, &[Position::new(2, 0, 0)], 1));
+    }
+
+    #[test]
+    fn test_schedule_detects_deadlock() {
+        let positions = [Position::new(0, 0, 0), Position::new(1, 0, 0)];
+        let plans = [vec![smove(Axis::X, 1)], vec![smove(Axis::X, -1)]];
+
+        assert!(schedule(&positions, &plans).is_err());
+    }
+}
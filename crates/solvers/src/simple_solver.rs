@@ -1,13 +1,28 @@
-use crate::{SolverResult, SolverState, SolverType, SOLVERS};
+use crate::{ProblemKind, Solver, SolverResult, SolverState, SOLVERS};
+use commands::Command;
 use linkme::distributed_slice;
 use std::time::Instant;
 
 #[distributed_slice(SOLVERS)]
-static SIMPLE_SOLVER: SolverType = simple_solver;
+static SIMPLE_SOLVER: Solver = Solver {
+    name: "simple",
+    kinds: &[
+        ProblemKind::Assembly,
+        ProblemKind::Disassembly,
+        ProblemKind::Reassembly,
+    ],
+    run: simple_solver,
+};
 
-fn simple_solver(_state: &SolverState) -> anyhow::Result<SolverResult> {
+/// Halts right away, which only solves problems whose source already
+/// matches the target.
+fn simple_solver(state: &SolverState) -> anyhow::Result<SolverResult> {
     let start = Instant::now();
+    if state.source != state.target {
+        return Err(anyhow::anyhow!("Source doesn't match the target"));
+    }
     Ok(SolverResult {
         elapsed: start.elapsed(),
+        trace: vec![Command::Halt],
     })
 }
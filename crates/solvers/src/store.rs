@@ -0,0 +1,168 @@
+use crate::portfolio::Verified;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INDEX: &str = "index.json";
+
+/// Best known trace of a problem.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Trace file name, relative to the store directory.
+    pub trace_file: String,
+    pub energy: i64,
+    pub solver: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Git revision of the code that produced the trace.
+    pub revision: String,
+}
+
+/// Directory of best traces keyed by problem id (e.g. `FA001`), listed in
+/// an `index.json` file next to them.
+#[derive(Debug)]
+pub struct Store {
+    root: PathBuf,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Store {
+    /// Opens the store at `root`, creating the directory when missing.
+    pub fn open(root: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+
+        let index = root.join(INDEX);
+        let entries = if index.exists() {
+            serde_json::from_reader(BufReader::new(File::open(index)?))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { root, entries })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn best(&self, problem: &str) -> Option<&Entry> {
+        self.entries.get(problem)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &Entry)> {
+        self.entries.iter()
+    }
+
+    /// Records a verified trace for `problem` when it's strictly cheaper than
+    /// the stored one, returning whether it was.
+    pub fn record(&mut self, problem: &str, verified: &Verified) -> anyhow::Result<bool> {
+        if self
+            .best(problem)
+            .is_some_and(|best| best.energy <= verified.energy)
+        {
+            return Ok(false);
+        }
+
+        let trace_file = format!("{problem}.nbt");
+        write_atomically(&self.root.join(&trace_file), |writer| {
+            nbt::write_commands(writer, &verified.trace)
+        })?;
+
+        self.entries.insert(
+            problem.to_string(),
+            Entry {
+                trace_file,
+                energy: verified.energy,
+                solver: verified.solver.to_string(),
+                timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                revision: current_revision().unwrap_or_else(|| "unknown".to_string()),
+            },
+        );
+        write_atomically(&self.root.join(INDEX), |writer| {
+            serde_json::to_writer_pretty(&mut *writer, &self.entries)?;
+            Ok(())
+        })?;
+
+        Ok(true)
+    }
+
+    /// Copies the best trace of every problem to `directory` as
+    /// `<problem>.nbt`, the layout submissions use.
+    pub fn export(&self, directory: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(directory)?;
+        for (problem, entry) in &self.entries {
+            fs::copy(
+                self.root.join(&entry.trace_file),
+                directory.join(format!("{problem}.nbt")),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes `path` through a temporary file, so an interrupted write never
+/// leaves a truncated trace or index behind.
+fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let temporary = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+    write(&mut writer)?;
+    writer.flush()?;
+    fs::rename(temporary, path)?;
+    Ok(())
+}
+
+fn current_revision() -> Option<String> {
+    let output = process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use commands::Command;
+    use std::time::Duration;
+
+    fn verified(solver: &'static str, energy: i64) -> Verified {
+        Verified {
+            solver,
+            trace: vec![Command::Flip, Command::Flip, Command::Halt],
+            energy,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_store_keeps_cheapest_trace() -> anyhow::Result<()> {
+        let directory = tempfile::tempdir()?;
+        let mut store = Store::open(directory.path().join("store"))?;
+
+        assert!(store.record("FA001", &verified("gfill", 100))?);
+        assert!(!store.record("FA001", &verified("strip", 100))?);
+        assert!(store.record("FA001", &verified("strip", 90))?);
+
+        let reopened = Store::open(directory.path().join("store"))?;
+        let best = reopened.best("FA001").unwrap();
+        assert_eq!(90, best.energy);
+        assert_eq!("strip", best.solver);
+
+        let export = directory.path().join("export");
+        reopened.export(&export)?;
+        let trace = fs::read(export.join("FA001.nbt"))?;
+        assert_eq!(vec![0b11111101, 0b11111101, 0b11111111], trace);
+        Ok(())
+    }
+}
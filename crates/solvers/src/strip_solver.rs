@@ -0,0 +1,377 @@
+use crate::harmonics::minimize_harmonics;
+use crate::moves::{moves_between, push_lockstep, straight_moves, Axis};
+use crate::scheduler::schedule;
+use crate::{ProblemKind, Solver, SolverResult, SolverState, SOLVERS};
+use bot::Position;
+use commands::{Command, Difference, Fill, Fission, FusionP, FusionS};
+use linkme::distributed_slice;
+use mdl::Matrix;
+use state::reverse_trace;
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+#[distributed_slice(SOLVERS)]
+static STRIP_SOLVER: Solver = Solver {
+    name: "strip",
+    kinds: &[ProblemKind::Assembly],
+    run: strip_solver,
+};
+
+fn strip_solver(state: &SolverState) -> anyhow::Result<SolverResult> {
+    let start = Instant::now();
+    state.expect_kind(ProblemKind::Assembly)?;
+    let mut trace = strip_trace(&state.target, state.max_bots)?;
+    // Flips are only moved around when there's time left.
+    if !state.cancellation.is_cancelled() {
+        trace = minimize_harmonics(&state.source, state.max_bots, &trace)?;
+    }
+    Ok(SolverResult {
+        elapsed: start.elapsed(),
+        trace,
+    })
+}
+
+/// Assembles `target` with up to `max_bots` bots, each owning a strip of
+/// columns along the longer of x and z and filling it layer by layer from
+/// above, all bots in lockstep between layers. Runs in High harmonic
+/// between the first fission and the final fusion. Relies on the problem
+/// guarantee that the model stays off the x=0 and z=0 planes and below
+/// y=R-1, which are used for travel. There's no search involved, so solvers
+/// cancelled before they have a trace fall back on it.
+pub fn strip_trace(target: &Matrix, max_bots: u8) -> anyhow::Result<Vec<Command>> {
+    let Some(bounds) = Bounds::of(target) else {
+        return Ok(vec![Command::Halt]);
+    };
+    if *bounds.x.start() == 0 || *bounds.z.start() == 0 || bounds.max_y + 1 >= target.r {
+        return Err(anyhow::anyhow!(
+            "Model touches the travel planes, bounds {bounds:?}"
+        ));
+    }
+
+    let layout = if bounds.z.clone().count() > bounds.x.clone().count() {
+        Layout {
+            strip: Axis::Z,
+            row: Axis::X,
+        }
+    } else {
+        Layout {
+            strip: Axis::X,
+            row: Axis::Z,
+        }
+    };
+    let (columns, rows) = match layout.strip {
+        Axis::Z => (bounds.z.clone(), bounds.x.clone()),
+        _ => (bounds.x.clone(), bounds.z.clone()),
+    };
+
+    let weights = columns
+        .clone()
+        .map(|column| {
+            (0..=bounds.max_y)
+                .flat_map(|y| rows.clone().map(move |row| (y, row)))
+                .filter(|(y, row)| layout.is_filled(target, column, *y, *row))
+                .count()
+        })
+        .collect::<Vec<usize>>();
+    let bot_count = (max_bots as usize).clamp(1, weights.len());
+    let strips = split_strips(&weights, bot_count)
+        .into_iter()
+        .map(|strip| strip.start() + columns.start()..=strip.end() + columns.start())
+        .collect::<Vec<_>>();
+
+    let mut trace = vec![Command::Flip];
+    let mut positions = spawn_bots(&mut trace, &layout, &strips);
+
+    // Each layer is scheduled on its own, so bots finishing it early wait
+    // for the others before starting the next one.
+    for y in 0..=bounds.max_y {
+        let starts = positions.clone();
+        let plans = positions
+            .iter_mut()
+            .zip(&strips)
+            .map(|(position, strip)| fill_layer(target, &layout, position, strip, &rows, y))
+            .collect::<Vec<_>>();
+        trace.extend(schedule(&starts, &plans)?);
+    }
+
+    gather_bots(&mut trace, &layout, &mut positions, &strips);
+
+    let home = &positions[0];
+    trace.extend(moves_between(
+        home,
+        &Position::zero(),
+        [layout.row, layout.strip, Axis::Y],
+    ));
+    trace.push(Command::Flip);
+    trace.push(Command::Halt);
+
+    Ok(trace)
+}
+
+/// Clears `source` by reversing the strip trace assembling it, the fallback
+/// of cancelled disassembly solvers.
+pub fn unstrip_trace(source: &Matrix, max_bots: u8) -> anyhow::Result<Vec<Command>> {
+    let assembly = strip_trace(source, max_bots)?;
+    reverse_trace(&Matrix::new(source.r), max_bots, &assembly)
+}
+
+#[derive(Debug)]
+struct Bounds {
+    x: RangeInclusive<usize>,
+    z: RangeInclusive<usize>,
+    max_y: usize,
+}
+
+impl Bounds {
+    fn of(matrix: &Matrix) -> Option<Self> {
+        let (min, max) = matrix.bounds()?;
+        Some(Self {
+            x: min[0]..=max[0],
+            z: min[2]..=max[2],
+            max_y: max[1],
+        })
+    }
+}
+
+/// Which horizontal axis strips are cut along, `row` being the other one.
+#[derive(Debug)]
+struct Layout {
+    strip: Axis,
+    row: Axis,
+}
+
+impl Layout {
+    fn position(&self, column: usize, y: usize, row: usize) -> Position {
+        let position = Position::new(0, y as u8, 0);
+        let position = self.strip.with_coordinate(&position, column as i32);
+        self.row.with_coordinate(&position, row as i32)
+    }
+
+    fn is_filled(&self, target: &Matrix, column: usize, y: usize, row: usize) -> bool {
+        let position = self.position(column, y, row);
+        target.is_filled(
+            position.x as usize,
+            position.y as usize,
+            position.z as usize,
+        )
+    }
+
+    fn near(&self, distance: i32) -> Difference {
+        let (dx, dy, dz) = self.strip.difference(distance);
+        Difference::near(dx, dy, dz)
+    }
+}
+
+/// Splits columns into `count` contiguous non-empty strips with roughly
+/// equal total weight.
+fn split_strips(weights: &[usize], count: usize) -> Vec<RangeInclusive<usize>> {
+    let mut prefix = vec![0];
+    for weight in weights {
+        prefix.push(prefix.last().unwrap() + weight);
+    }
+    let total = *prefix.last().unwrap();
+
+    let mut bounds = vec![0];
+    for k in 1..count {
+        let previous = *bounds.last().unwrap();
+        let goal = total * k / count;
+        let end = (previous + 1..=weights.len() - (count - k))
+            .min_by_key(|&end| prefix[end].abs_diff(goal))
+            .unwrap();
+        bounds.push(end);
+    }
+    bounds.push(weights.len());
+
+    bounds
+        .windows(2)
+        .map(|window| window[0]..=window[1] - 1)
+        .collect()
+}
+
+/// Walks the first bot to the first strip and fissions a chain along the
+/// y=0 line of the strip axis, so bot `i + 1` ends at the start of strip `i`.
+fn spawn_bots(
+    trace: &mut Vec<Command>,
+    layout: &Layout,
+    strips: &[RangeInclusive<usize>],
+) -> Vec<Position> {
+    let mut positions = vec![layout.position(*strips[0].start(), 0, 0)];
+    trace.extend(straight_moves(layout.strip, *strips[0].start() as i32));
+
+    for i in 0..strips.len() - 1 {
+        let mut plans = vec![vec![]; positions.len()];
+        plans[i].push(Command::Fission(Fission {
+            nd: layout.near(1),
+            m: (strips.len() - 2 - i) as u8,
+        }));
+        push_lockstep(trace, &plans);
+
+        let child = *strips[i].start() as i32 + 1;
+        let mut plans = vec![vec![]; positions.len() + 1];
+        plans[i + 1] = straight_moves(layout.strip, *strips[i + 1].start() as i32 - child);
+        push_lockstep(trace, &plans);
+
+        positions.push(layout.position(*strips[i + 1].start(), 0, 0));
+    }
+
+    positions
+}
+
+/// Plans one bot filling layer `y` of its strip from height `y + 1`,
+/// moving in a serpentine over the rows that have something to fill.
+fn fill_layer(
+    target: &Matrix,
+    layout: &Layout,
+    position: &mut Position,
+    strip: &RangeInclusive<usize>,
+    rows: &RangeInclusive<usize>,
+    y: usize,
+) -> Vec<Command> {
+    let mut plan = straight_moves(Axis::Y, 1);
+    position.y += 1;
+
+    let reverse_columns = y % 2 == 1;
+    let columns: Vec<usize> = if reverse_columns {
+        strip.clone().rev().collect()
+    } else {
+        strip.clone().collect()
+    };
+
+    for (index, column) in columns.into_iter().enumerate() {
+        let cells: Vec<usize> = if index % 2 == 1 {
+            rows.clone().rev().collect()
+        } else {
+            rows.clone().collect()
+        };
+
+        for row in cells
+            .into_iter()
+            .filter(|&row| layout.is_filled(target, column, y, row))
+        {
+            let next = layout.position(column, position.y as usize, row);
+            plan.extend(moves_between(
+                position,
+                &next,
+                [layout.strip, layout.row, Axis::Y],
+            ));
+            plan.push(Command::Fill(Fill {
+                nd: Difference::near(0, -1, 0),
+            }));
+            *position = next;
+        }
+    }
+
+    plan
+}
+
+/// Brings every bot back to the start of its strip on the row=0 line and
+/// fuses the chain back into the first bot, last bot first.
+fn gather_bots(
+    trace: &mut Vec<Command>,
+    layout: &Layout,
+    positions: &mut Vec<Position>,
+    strips: &[RangeInclusive<usize>],
+) {
+    let plans = positions
+        .iter_mut()
+        .zip(strips)
+        .map(|(position, strip)| {
+            let next = layout.position(*strip.start(), position.y as usize, 0);
+            let plan = moves_between(position, &next, [layout.row, layout.strip, Axis::Y]);
+            *position = next;
+            plan
+        })
+        .collect::<Vec<_>>();
+    push_lockstep(trace, &plans);
+
+    while positions.len() > 1 {
+        let last = positions.len() - 1;
+        let next = layout.strip.with_coordinate(
+            &positions[last],
+            layout.strip.coordinate(&positions[last - 1]) + 1,
+        );
+
+        let mut plans = vec![vec![]; positions.len()];
+        plans[last] = moves_between(&positions[last], &next, [layout.strip, Axis::Y, layout.row]);
+        push_lockstep(trace, &plans);
+
+        let mut plans = vec![vec![]; positions.len()];
+        plans[last - 1].push(Command::FusionP(FusionP { nd: layout.near(1) }));
+        plans[last].push(Command::FusionS(FusionS {
+            nd: layout.near(-1),
+        }));
+        push_lockstep(trace, &plans);
+
+        positions.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdl::CellState;
+    use state::State;
+    use std::io::Cursor;
+
+    fn assemble(model: &Matrix, max_bots: u8) -> anyhow::Result<State> {
+        let trace = strip_trace(model, max_bots)?;
+        let mut state = State::new(max_bots, Matrix::new(model.r));
+        state.execute(&trace)?;
+        Ok(state)
+    }
+
+    #[test]
+    fn test_strip_solver_builds_model() -> anyhow::Result<()> {
+        let data = include_bytes!("../../../data/FA001_tgt.mdl");
+        let model = mdl::read_matrix(&mut Cursor::new(data))?;
+
+        let single = assemble(&model, 1)?;
+        let multi = assemble(&model, 20)?;
+
+        assert!(multi.halted);
+        assert_eq!(model, multi.matrix);
+        assert_eq!(model, single.matrix);
+        assert!(multi.steps < single.steps);
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_solver_builds_generated_models() -> anyhow::Result<()> {
+        for family in mdl::Family::ALL {
+            let model = mdl::generate(family, 16, 7)?;
+            let state = assemble(&model, 20)?;
+
+            assert!(state.halted, "{family:?} doesn't halt");
+            assert_eq!(model, state.matrix, "{family:?} isn't built");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_solver_splits_along_longer_axis() -> anyhow::Result<()> {
+        // A wall one voxel thick in x, long in z.
+        let mut model = Matrix::new(20);
+        for y in 0..4 {
+            for z in 1..19 {
+                model.set(5, y, z, CellState::Fill);
+            }
+        }
+
+        let single = assemble(&model, 1)?;
+        let multi = assemble(&model, 8)?;
+
+        assert!(multi.halted);
+        assert_eq!(model, multi.matrix);
+        assert!(multi.steps < single.steps);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_strips() {
+        let strips = split_strips(&[1, 1, 10, 1, 1], 3);
+        assert_eq!(vec![0..=1, 2..=2, 3..=4], strips);
+
+        let strips = split_strips(&[0, 0, 0], 3);
+        assert_eq!(vec![0..=0, 1..=1, 2..=2], strips);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::moves::{moves_between, Axis};
+use bot::Position;
+use commands::{
+    Command, Difference, Fill, Fission, FusionP, FusionS, GFill, GVoid, LMove, SMove, Void,
+};
+use mdl::Symmetry;
+
+/// Maps a trace for models of resolution `r` through `symmetry`, so it
+/// builds the transformed target from the transformed source. The symmetry
+/// moves the origin, where bots start and halt, to another corner of the
+/// floor, so the first bot walks there first and back before halting, along
+/// the edges of the floor that models leave free.
+pub fn transform_trace(
+    trace: &[Command],
+    r: usize,
+    symmetry: Symmetry,
+) -> anyhow::Result<Vec<Command>> {
+    let Some((Command::Halt, commands)) = trace.split_last() else {
+        return Err(anyhow::anyhow!("Trace doesn't end with Halt"));
+    };
+
+    let origin = Position::new(0, 0, 0);
+    let [x, y, z] = symmetry.position(r, [0, 0, 0]);
+    let corner = Position::new(x as u8, y as u8, z as u8);
+
+    let mut transformed = moves_between(&origin, &corner, [Axis::X, Axis::Z, Axis::Y]);
+    transformed.extend(
+        commands
+            .iter()
+            .map(|command| transform_command(command, symmetry)),
+    );
+    transformed.extend(moves_between(&corner, &origin, [Axis::X, Axis::Z, Axis::Y]));
+    transformed.push(Command::Halt);
+    Ok(transformed)
+}
+
+fn transform_command(command: &Command, symmetry: Symmetry) -> Command {
+    let d = |difference: &Difference| {
+        let [dx, dy, dz] = symmetry.difference([difference.dx, difference.dy, difference.dz]);
+        Difference::new(dx, dy, dz, difference.kind)
+    };
+
+    match command {
+        Command::Halt | Command::Wait | Command::Flip => command.clone(),
+        Command::SMove(m) => Command::SMove(SMove { lld: d(&m.lld) }),
+        Command::LMove(m) => Command::LMove(LMove {
+            sld1: d(&m.sld1),
+            sld2: d(&m.sld2),
+        }),
+        Command::FusionP(fusion) => Command::FusionP(FusionP { nd: d(&fusion.nd) }),
+        Command::FusionS(fusion) => Command::FusionS(FusionS { nd: d(&fusion.nd) }),
+        Command::Fission(fission) => Command::Fission(Fission {
+            nd: d(&fission.nd),
+            m: fission.m,
+        }),
+        Command::Fill(fill) => Command::Fill(Fill { nd: d(&fill.nd) }),
+        Command::Void(void) => Command::Void(Void { nd: d(&void.nd) }),
+        Command::GFill(fill) => Command::GFill(GFill {
+            nd: d(&fill.nd),
+            fd: d(&fill.fd),
+        }),
+        Command::GVoid(void) => Command::GVoid(GVoid {
+            nd: d(&void.nd),
+            fd: d(&void.fd),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdl::Matrix;
+    use state::State;
+    use std::io::Cursor;
+
+    fn build(r: usize, trace: &[Command]) -> anyhow::Result<Matrix> {
+        let mut state = State::new(20, Matrix::new(r));
+        state.execute(trace)?;
+        assert!(state.halted);
+        Ok(state.matrix)
+    }
+
+    #[test]
+    fn test_transformed_trace_builds_transformed_model() -> anyhow::Result<()> {
+        let model = mdl::read_matrix(&mut Cursor::new(include_bytes!(
+            "../../../data/FA002_tgt.mdl"
+        )))?;
+        let trace =
+            nbt::read_commands(&mut Cursor::new(include_bytes!("../../../data/FA002.nbt")))?;
+
+        for symmetry in [
+            Symmetry::RotateY(1),
+            Symmetry::RotateY(2),
+            Symmetry::MirrorX,
+            Symmetry::MirrorZ,
+        ] {
+            let transformed = transform_trace(&trace, model.r, symmetry)?;
+            assert_eq!(model.transformed(symmetry), build(model.r, &transformed)?);
+
+            let back = transform_trace(&transformed, model.r, symmetry.inverse())?;
+            assert_eq!(model, build(model.r, &back)?);
+        }
+        Ok(())
+    }
+}
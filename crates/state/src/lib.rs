@@ -1,10 +1,18 @@
-use bot::Bot;
-use commands::{Command, Mlen};
+mod reverse;
+mod volatile;
+
+pub use reverse::reverse_trace;
+pub use volatile::{offset, volatile};
+
+use anyhow::anyhow;
+use bot::{Bot, Position};
+use commands::{Command, Difference, Mlen};
 use log::trace;
-use mdl::{CellState, Matrix};
-use std::collections::HashMap;
+use mdl::{CellState, Matrix, NEIGHBOURS};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Harmonic {
     Low,
     High,
@@ -17,12 +25,28 @@ pub struct State {
     pub energy: i64,
     pub energy_spend_type: HashMap<&'static str, i64>,
     pub current_bot_count: usize,
+    pub steps: usize,
+    pub halted: bool,
+    /// Whether every Full voxel is grounded at the end of the last step.
+    pub grounded: bool,
+    /// Full voxels with no face-adjacent path to the floor, kept up to date
+    /// step by step so `grounded` never needs a pass over the whole model.
+    floating: HashSet<(usize, usize, usize)>,
+    /// Voxels filled and voided during the current step, to update
+    /// `floating`.
+    filled: Vec<(usize, usize, usize)>,
+    voided: Vec<(usize, usize, usize)>,
 }
 
 impl State {
     pub fn new(max_bots: u8, matrix: Matrix) -> Self {
         let mut bots: Vec<Option<Bot>> = (0..max_bots).map(|_| None).collect();
         bots[0] = Some(Bot::initial(max_bots));
+        let floating = matrix
+            .fill_order()
+            .floating
+            .into_iter()
+            .collect::<HashSet<_>>();
         Self {
             bots,
             harmonic: Harmonic::Low,
@@ -30,9 +54,32 @@ impl State {
             energy: 0,
             energy_spend_type: HashMap::new(),
             current_bot_count: 1,
+            steps: 0,
+            halted: false,
+            grounded: floating.is_empty(),
+            floating,
+            filled: vec![],
+            voided: vec![],
         }
     }
 
+    pub fn bot(&self, bid: usize) -> Option<&Bot> {
+        self.bots.get(bid - 1).and_then(Option::as_ref)
+    }
+
+    fn bot_mut(&mut self, bid: usize) -> anyhow::Result<&mut Bot> {
+        self.bots
+            .get_mut(bid - 1)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| anyhow!("Bot {bid} is not active"))
+    }
+
+    /// Bids of active bots in ascending order, which is the order their
+    /// commands appear in a step of the trace.
+    pub fn active_bids(&self) -> Vec<usize> {
+        self.bots.iter().flatten().map(|bot| bot.index).collect()
+    }
+
     pub fn apply_energy(&mut self, energy_type: &'static str, energy: i64) {
         self.energy += energy;
         self.energy_spend_type
@@ -41,7 +88,9 @@ impl State {
             .or_insert(energy);
     }
 
-    pub fn end_step(&mut self) {
+    /// Charges the global cost of a step, based on harmonics and bot count
+    /// at the beginning of the step.
+    pub fn apply_step_energy(&mut self) {
         let energy = self.matrix.r.pow(3)
             * match self.harmonic {
                 Harmonic::Low => 3,
@@ -51,86 +100,618 @@ impl State {
         self.apply_energy("step", energy as i64);
         self.apply_energy("active_bot", 20 * self.current_bot_count as i64);
     }
+
+    /// Executes a single time step, `commands` holds one command per active
+    /// bot in bid order.
+    pub fn step(&mut self, commands: &[Command]) -> anyhow::Result<()> {
+        let bids = self.active_bids();
+        if commands.len() != bids.len() {
+            return Err(anyhow!(
+                "Step {} expects {} commands, got {}",
+                self.steps,
+                bids.len(),
+                commands.len()
+            ));
+        }
+
+        self.apply_step_energy();
+
+        let positions = bids
+            .iter()
+            .map(|bid| self.bot_mut(*bid).map(|bot| bot.position().clone()))
+            .collect::<anyhow::Result<Vec<Position>>>()?;
+        let fusions = pair_fusions(&bids, &positions, commands)?;
+        let regions = group_regions(&bids, &positions, commands)?;
+        self.check_volatile(&bids, &positions, commands, &regions)?;
+
+        for (bid, command) in bids.iter().zip(commands) {
+            match command {
+                Command::FusionP(_)
+                | Command::FusionS(_)
+                | Command::GFill(_)
+                | Command::GVoid(_) => {}
+                _ => command.apply(self, *bid)?,
+            }
+        }
+
+        for (primary, secondary) in fusions {
+            self.fuse(primary, secondary)?;
+        }
+
+        for (region, cell_state) in regions {
+            self.set_region(&region, &cell_state);
+        }
+
+        self.update_grounded();
+        if self.harmonic == Harmonic::Low && !self.grounded {
+            return Err(anyhow!(
+                "Model isn't grounded at the end of step {} in Low harmonic",
+                self.steps
+            ));
+        }
+
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Checks that the trace halted with `target` built, describing the
+    /// mismatched voxels otherwise.
+    pub fn check_target(&self, target: &Matrix) -> anyhow::Result<()> {
+        if !self.halted {
+            return Err(anyhow!("Trace doesn't halt"));
+        }
+        let diff = self.matrix.diff(target);
+        if !diff.is_empty() {
+            return Err(anyhow!("Trace doesn't produce the target: {diff}"));
+        }
+        Ok(())
+    }
+
+    /// Runs the flat trace step by step until it is exhausted or a bot halts.
+    pub fn execute(&mut self, trace: &[Command]) -> anyhow::Result<()> {
+        let mut commands = trace;
+
+        while !commands.is_empty() && !self.halted {
+            let count = self.current_bot_count;
+            if commands.len() < count {
+                return Err(anyhow!(
+                    "Trace ended in the middle of step {}, {} commands left for {count} bots",
+                    self.steps,
+                    commands.len()
+                ));
+            }
+
+            let (step, rest) = commands.split_at(count);
+            self.step(step)?;
+            commands = rest;
+        }
+
+        Ok(())
+    }
+
+    fn fission(&mut self, bid: usize, nd: &Difference, m: u8) -> anyhow::Result<()> {
+        let bot = self.bot_mut(bid)?;
+        let m = m as usize;
+        if bot.seeds.len() < m + 1 {
+            return Err(anyhow!(
+                "Bot {bid} has {} seeds, can't fission with m={m}",
+                bot.seeds.len()
+            ));
+        }
+
+        let seeds = std::mem::take(&mut bot.seeds);
+        bot.seeds = seeds[m + 1..].to_vec();
+        let position = bot.get_position_by_diff(nd);
+
+        let child_bid = seeds[0] as usize;
+        trace!("fission {bid} -> {child_bid} at {position:?}");
+        self.bots[child_bid - 1] = Some(Bot::new(child_bid, seeds[1..=m].to_vec(), position));
+        self.current_bot_count += 1;
+        self.apply_energy("fission", 24);
+
+        Ok(())
+    }
+
+    /// Checks that no two bots or groups claim the same cell during a step,
+    /// that everything stays within the matrix and that moves and fissions
+    /// only go through Void cells.
+    fn check_volatile(
+        &self,
+        bids: &[usize],
+        positions: &[Position],
+        commands: &[Command],
+        regions: &[(Region, CellState)],
+    ) -> anyhow::Result<()> {
+        let r = self.matrix.r;
+        let mut owners: HashMap<Position, usize> = HashMap::new();
+
+        for ((bid, position), command) in bids.iter().zip(positions).zip(commands) {
+            let cells = volatile(position, command)?;
+            let entering = matches!(
+                command,
+                Command::SMove(_) | Command::LMove(_) | Command::Fission(_)
+            );
+
+            for (i, cell) in cells.into_iter().enumerate() {
+                let (x, y, z) = (cell.x as usize, cell.y as usize, cell.z as usize);
+                if x >= r || y >= r || z >= r {
+                    return Err(anyhow!("Bot {bid} reaches {cell:?} outside of the matrix"));
+                }
+                if entering && i > 0 && self.matrix.is_filled(x, y, z) {
+                    return Err(anyhow!("Bot {bid} runs {command:?} into Full {cell:?}"));
+                }
+                if let Some(other) = owners.insert(cell.clone(), *bid) {
+                    return Err(anyhow!("Bots {other} and {bid} interfere at {cell:?}"));
+                }
+            }
+        }
+
+        let mut regions_cells = HashSet::new();
+        for (region, _) in regions {
+            if region.max.x as usize >= r
+                || region.max.y as usize >= r
+                || region.max.z as usize >= r
+            {
+                return Err(anyhow!("Region {region:?} is outside of the matrix"));
+            }
+
+            for x in region.min.x..=region.max.x {
+                for y in region.min.y..=region.max.y {
+                    for z in region.min.z..=region.max.z {
+                        let cell = Position::new(x, y, z);
+                        if owners.contains_key(&cell) || !regions_cells.insert(cell.clone()) {
+                            return Err(anyhow!("Region {region:?} interferes at {cell:?}"));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Updates `floating` and `grounded` after a step, only looking at the
+    /// voxels around the ones that changed. Voids are handled as if the
+    /// step's fills hadn't happened yet: every grounded neighbour of a voided
+    /// voxel searches its way down to the floor, and the voxels of a search
+    /// that doesn't get there float. Fills then float until a flood from the
+    /// ones touching the floor or a grounded voxel reaches them, grounding
+    /// any floating voxel they connect on the way.
+    fn update_grounded(&mut self) {
+        let filled = std::mem::take(&mut self.filled);
+        let voided = std::mem::take(&mut self.voided);
+        let added = filled.iter().cloned().collect::<HashSet<_>>();
+
+        let mut supported = HashSet::new();
+        for voxel in &voided {
+            self.floating.remove(voxel);
+        }
+        for voxel in &voided {
+            for next in neighbours(self.matrix.r, *voxel) {
+                let (x, y, z) = next;
+                if self.matrix.is_filled(x, y, z)
+                    && !added.contains(&next)
+                    && !self.floating.contains(&next)
+                    && !supported.contains(&next)
+                {
+                    let (reached, component) = self.search_floor(next, &added, &supported);
+                    if reached {
+                        supported.extend(component);
+                    } else {
+                        self.floating.extend(component);
+                    }
+                }
+            }
+        }
+
+        self.floating.extend(filled.iter().cloned());
+        let mut queue = filled
+            .into_iter()
+            .filter(|voxel| {
+                voxel.1 == 0
+                    || neighbours(self.matrix.r, *voxel).any(|(x, y, z)| {
+                        self.matrix.is_filled(x, y, z) && !self.floating.contains(&(x, y, z))
+                    })
+            })
+            .collect::<Vec<_>>();
+        for voxel in &queue {
+            self.floating.remove(voxel);
+        }
+
+        while let Some(voxel) = queue.pop() {
+            for next in neighbours(self.matrix.r, voxel) {
+                if self.floating.remove(&next) {
+                    queue.push(next);
+                }
+            }
+        }
+
+        self.grounded = self.floating.is_empty();
+    }
+
+    /// Searches from `start` through Full voxels that were grounded before
+    /// the step, lowest first, until it gets to the floor or a voxel known to
+    /// be `supported`. Returns whether it did and the voxels it went through.
+    fn search_floor(
+        &self,
+        start: (usize, usize, usize),
+        added: &HashSet<(usize, usize, usize)>,
+        supported: &HashSet<(usize, usize, usize)>,
+    ) -> (bool, HashSet<(usize, usize, usize)>) {
+        let mut visited = HashSet::from([start]);
+        let mut queue = BinaryHeap::from([(Reverse(start.1), start)]);
+
+        while let Some((_, voxel)) = queue.pop() {
+            if voxel.1 == 0 || supported.contains(&voxel) {
+                return (true, visited);
+            }
+            for next in neighbours(self.matrix.r, voxel) {
+                let (x, y, z) = next;
+                if self.matrix.is_filled(x, y, z)
+                    && !added.contains(&next)
+                    && !self.floating.contains(&next)
+                    && visited.insert(next)
+                {
+                    queue.push((Reverse(next.1), next));
+                }
+            }
+        }
+
+        (false, visited)
+    }
+
+    fn set_region(&mut self, region: &Region, cell_state: &CellState) {
+        trace!("{cell_state:?} {region:?}");
+        for x in region.min.x..=region.max.x {
+            for y in region.min.y..=region.max.y {
+                for z in region.min.z..=region.max.z {
+                    let (x, y, z) = (x as usize, y as usize, z as usize);
+                    match (cell_state, self.matrix.is_filled(x, y, z)) {
+                        (CellState::Fill, true) => self.apply_energy("gfill", 6),
+                        (CellState::Fill, false) => {
+                            self.filled.push((x, y, z));
+                            self.apply_energy("gfill", 12)
+                        }
+                        (CellState::Void, true) => {
+                            self.voided.push((x, y, z));
+                            self.apply_energy("gvoid", -12)
+                        }
+                        (CellState::Void, false) => self.apply_energy("gvoid", 3),
+                    }
+                    self.matrix.set(x, y, z, cell_state.clone());
+                }
+            }
+        }
+    }
+
+    fn fuse(&mut self, primary: usize, secondary: usize) -> anyhow::Result<()> {
+        trace!("fusion {secondary} -> {primary}");
+        let secondary = self.bots[secondary - 1]
+            .take()
+            .ok_or_else(|| anyhow!("Bot {secondary} is not active"))?;
+        let bot = self.bot_mut(primary)?;
+        bot.seeds.push(secondary.index as u8);
+        bot.seeds.extend(secondary.seeds);
+        bot.seeds.sort();
+
+        self.current_bot_count -= 1;
+        self.apply_energy("fusion", -24);
+
+        Ok(())
+    }
+}
+
+/// Face-adjacent voxels of `(x, y, z)` inside a model of resolution `r`.
+fn neighbours(
+    r: usize,
+    (x, y, z): (usize, usize, usize),
+) -> impl Iterator<Item = (usize, usize, usize)> {
+    let r = r as i64;
+    NEIGHBOURS.into_iter().filter_map(move |(dx, dy, dz)| {
+        let (nx, ny, nz) = (x as i64 + dx, y as i64 + dy, z as i64 + dz);
+        [nx, ny, nz].iter().all(|v| (0..r).contains(v)).then_some((
+            nx as usize,
+            ny as usize,
+            nz as usize,
+        ))
+    })
+}
+
+/// Matches every `FusionP` with the `FusionS` of the bot it points at,
+/// returning `(primary, secondary)` bid pairs.
+fn pair_fusions(
+    bids: &[usize],
+    positions: &[Position],
+    commands: &[Command],
+) -> anyhow::Result<Vec<(usize, usize)>> {
+    let mut fusions = vec![];
+
+    for (i, command) in commands.iter().enumerate() {
+        if let Command::FusionP(fusion) = command {
+            let secondary_position = offset(&positions[i], &fusion.nd)?;
+            let secondary = positions
+                .iter()
+                .position(|position| *position == secondary_position);
+
+            match secondary.map(|j| (j, &commands[j])) {
+                Some((j, Command::FusionS(other)))
+                    if offset(&positions[j], &other.nd).ok().as_ref() == Some(&positions[i]) =>
+                {
+                    fusions.push((bids[i], bids[j]));
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "FusionP of bot {} has no matching FusionS",
+                        bids[i]
+                    ))
+                }
+            }
+        }
+    }
+
+    let secondaries = commands
+        .iter()
+        .filter(|command| matches!(command, Command::FusionS(_)))
+        .count();
+    if secondaries != fusions.len() {
+        return Err(anyhow!("FusionS without matching FusionP"));
+    }
+
+    Ok(fusions)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub min: Position,
+    pub max: Position,
+}
+
+impl Region {
+    pub fn new(a: &Position, b: &Position) -> Self {
+        Self {
+            min: Position::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Position::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    pub fn dimension(&self) -> u32 {
+        [
+            self.min.x != self.max.x,
+            self.min.y != self.max.y,
+            self.min.z != self.max.z,
+        ]
+        .into_iter()
+        .filter(|differs| *differs)
+        .count() as u32
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        (self.min.x..=self.max.x).contains(&position.x)
+            && (self.min.y..=self.max.y).contains(&position.y)
+            && (self.min.z..=self.max.z).contains(&position.z)
+    }
+}
+
+/// Groups `GFill` and `GVoid` commands by the region they describe, checking
+/// that each region is designated by exactly one bot per corner and holds
+/// no bots.
+fn group_regions(
+    bids: &[usize],
+    positions: &[Position],
+    commands: &[Command],
+) -> anyhow::Result<Vec<(Region, CellState)>> {
+    let mut groups: HashMap<(Region, CellState), Vec<Position>> = HashMap::new();
+
+    for (i, command) in commands.iter().enumerate() {
+        let (nd, fd, cell_state) = match command {
+            Command::GFill(fill) => (&fill.nd, &fill.fd, CellState::Fill),
+            Command::GVoid(void) => (&void.nd, &void.fd, CellState::Void),
+            _ => continue,
+        };
+
+        let corner = offset(&positions[i], nd)?;
+        let opposite = offset(&corner, fd)?;
+        groups
+            .entry((Region::new(&corner, &opposite), cell_state))
+            .or_default()
+            .push(corner);
+    }
+
+    for ((region, _), corners) in &groups {
+        let unique = corners.iter().collect::<HashSet<_>>();
+        if unique.len() != corners.len() || corners.len() != 1 << region.dimension() {
+            return Err(anyhow!(
+                "Region {region:?} is designated by {} bots, {} distinct corners",
+                corners.len(),
+                unique.len()
+            ));
+        }
+
+        if let Some(i) = positions.iter().position(|p| region.contains(p)) {
+            return Err(anyhow!("Bot {} is inside of region {region:?}", bids[i]));
+        }
+    }
+
+    Ok(groups.into_keys().collect())
 }
 
 pub trait StateCommand {
-    fn apply(&self, state: &mut State) -> anyhow::Result<()>;
+    fn apply(&self, state: &mut State, bid: usize) -> anyhow::Result<()>;
 
     fn is_bot_command(&self) -> bool;
 }
 
 impl StateCommand for Command {
-    fn apply(&self, state: &mut State) -> anyhow::Result<()> {
+    fn apply(&self, state: &mut State, bid: usize) -> anyhow::Result<()> {
         match self {
-            Command::Halt => Ok(()),
+            Command::Halt => {
+                let bot = state.bot_mut(bid)?;
+                if *bot.position() != Position::zero() {
+                    return Err(anyhow!("Bot {bid} halted outside of origin"));
+                }
+                if state.current_bot_count != 1 {
+                    return Err(anyhow!("Halt with {} active bots", state.current_bot_count));
+                }
+                if state.harmonic != Harmonic::Low {
+                    return Err(anyhow!("Halt in High harmonic"));
+                }
+
+                state.halted = true;
+                Ok(())
+            }
             Command::Wait => Ok(()),
             Command::Flip => match state.harmonic {
                 Harmonic::Low => {
-                    println!("harmonic flip to High");
+                    trace!("harmonic flip to High");
                     state.harmonic = Harmonic::High;
                     Ok(())
                 }
                 Harmonic::High => {
-                    println!("harmonic flip to Low");
+                    trace!("harmonic flip to Low");
                     state.harmonic = Harmonic::Low;
                     Ok(())
                 }
             },
             Command::SMove(m) => {
-                trace!("smove {m:?}");
-                match &mut state.bots[0] {
-                    None => {}
-                    Some(bot) => {
-                        bot.apply_position_diff(&m.lld);
-                        state.apply_energy("smove", 2 * m.lld.mlen() as i64);
-                    }
-                }
-
+                trace!("smove {bid} {m:?}");
+                state.bot_mut(bid)?.apply_position_diff(&m.lld);
+                state.apply_energy("smove", 2 * m.lld.mlen() as i64);
                 Ok(())
             }
             Command::LMove(m) => {
+                trace!("lmove {bid} {m:?}");
+                let bot = state.bot_mut(bid)?;
+                bot.apply_position_diff(&m.sld1);
+                bot.apply_position_diff(&m.sld2);
                 state.apply_energy("lmove", 2 * (m.sld1.mlen() + 2 + m.sld2.mlen()) as i64);
                 Ok(())
             }
-            Command::FusionP(_) => Ok(()),
-            Command::FusionS(_) => Ok(()),
-            Command::Fission(_) => Ok(()),
+            Command::FusionP(_) | Command::FusionS(_) => Err(anyhow!(
+                "Fusion of bot {bid} must be applied in pairs by State::step"
+            )),
+            Command::Fission(fission) => state.fission(bid, &fission.nd, fission.m),
             Command::Fill(fill) => {
-                trace!("fill {fill:?}");
-                let _ = fill.nd;
-                match &state.bots[0] {
-                    None => {}
-                    Some(bot) => {
-                        let place = bot.get_position_by_diff(&fill.nd);
-                        state.matrix.set(
-                            place.x as usize,
-                            place.y as usize,
-                            place.z as usize,
-                            CellState::Fill,
-                        );
-                        state.apply_energy("fill", 12); // todo, check if already filled
-                    }
+                trace!("fill {bid} {fill:?}");
+                let place = state.bot_mut(bid)?.get_position_by_diff(&fill.nd);
+                let (x, y, z) = (place.x as usize, place.y as usize, place.z as usize);
+                if state.matrix.is_filled(x, y, z) {
+                    state.apply_energy("fill", 6);
+                } else {
+                    state.matrix.set(x, y, z, CellState::Fill);
+                    state.filled.push((x, y, z));
+                    state.apply_energy("fill", 12);
                 }
 
                 Ok(())
             }
             Command::Void(void) => {
-                trace!("void {void:?}");
-                let _ = void.nd;
-                state.apply_energy("fill", 12); // todo, check if already void
+                trace!("void {bid} {void:?}");
+                let place = state.bot_mut(bid)?.get_position_by_diff(&void.nd);
+                let (x, y, z) = (place.x as usize, place.y as usize, place.z as usize);
+                if state.matrix.is_filled(x, y, z) {
+                    state.matrix.set(x, y, z, CellState::Void);
+                    state.voided.push((x, y, z));
+                    state.apply_energy("void", -12);
+                } else {
+                    state.apply_energy("void", 3);
+                }
+
                 Ok(())
             }
-            Command::GFill(_) => Ok(()),
-            Command::GVoid(_) => Ok(()),
+            Command::GFill(_) | Command::GVoid(_) => Err(anyhow!(
+                "Group command of bot {bid} must be applied by its group in State::step"
+            )),
         }
     }
 
     fn is_bot_command(&self) -> bool {
-        match self {
-            Command::Halt => false,
-            Command::Wait => false,
-            Command::Flip => false,
-            _ => true,
+        !matches!(self, Command::Halt | Command::Wait | Command::Flip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use commands::{Fill, Fission, LMove, SMove, Void};
+
+    #[test]
+    fn test_step_tracks_groundedness() -> anyhow::Result<()> {
+        let fill = |dx, dy, dz| {
+            Command::Fill(Fill {
+                nd: Difference::near(dx, dy, dz),
+            })
+        };
+        let mut state = State::new(1, Matrix::new(5));
+
+        state.step(&[Command::Flip])?;
+        state.step(&[Command::SMove(SMove {
+            lld: Difference::long_linear(1, 0, 0),
+        })])?;
+        state.step(&[fill(0, 1, 1)])?;
+        assert!(!state.grounded);
+
+        state.step(&[fill(0, 0, 1)])?;
+        assert!(state.grounded);
+
+        state.step(&[fill(1, 0, 0)])?;
+        assert!(state.grounded);
+
+        state.step(&[Command::Void(Void {
+            nd: Difference::near(0, 0, 1),
+        })])?;
+        assert!(!state.grounded);
+        Ok(())
+    }
+
+    #[test]
+    fn test_step_tracks_groundedness_of_bridge() -> anyhow::Result<()> {
+        let mut matrix = Matrix::new(5);
+        for (x, y) in [(1, 0), (1, 1), (2, 1), (3, 1), (3, 0)] {
+            matrix.set(x, y, 1, CellState::Fill);
         }
+        let mut state = State::new(1, matrix);
+
+        state.step(&[Command::Flip])?;
+        state.step(&[Command::SMove(SMove {
+            lld: Difference::long_linear(2, 0, 0),
+        })])?;
+        state.step(&[Command::Void(Void {
+            nd: Difference::near(1, 0, 1),
+        })])?;
+        assert!(state.grounded);
+
+        state.step(&[Command::Void(Void {
+            nd: Difference::near(-1, 0, 1),
+        })])?;
+        assert!(!state.grounded);
+        assert_eq!(3, state.floating.len());
+
+        state.step(&[Command::Fill(Fill {
+            nd: Difference::near(1, 0, 1),
+        })])?;
+        assert!(state.grounded);
+        assert_eq!(state.matrix.is_grounded(), state.grounded);
+        Ok(())
+    }
+
+    #[test]
+    fn test_step_rejects_interference() -> anyhow::Result<()> {
+        let mut state = State::new(2, Matrix::new(5));
+        state.step(&[Command::Fission(Fission {
+            nd: Difference::near(1, 0, 0),
+            m: 0,
+        })])?;
+
+        let result = state.step(&[
+            Command::SMove(SMove {
+                lld: Difference::long_linear(0, 0, 2),
+            }),
+            Command::LMove(LMove {
+                sld1: Difference::short_linear(0, 0, 1),
+                sld2: Difference::short_linear(-1, 0, 0),
+            }),
+        ]);
+        assert!(result.is_err());
+        Ok(())
     }
 }
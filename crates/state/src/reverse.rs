@@ -0,0 +1,218 @@
+use crate::State;
+use anyhow::anyhow;
+use bot::{get_position_by_diff, Position};
+use commands::{Command, Fill, Fission, FusionP, FusionS, GFill, GVoid, LMove, SMove, Void};
+use mdl::Matrix;
+
+/// Bot as seen at the beginning of a step.
+#[derive(Debug)]
+struct FrameBot {
+    bid: usize,
+    position: Position,
+    seeds: usize,
+}
+
+fn frame(state: &State) -> Vec<FrameBot> {
+    state
+        .bots
+        .iter()
+        .flatten()
+        .map(|bot| FrameBot {
+            bid: bot.index,
+            position: bot.position().clone(),
+            seeds: bot.seeds.len(),
+        })
+        .collect()
+}
+
+/// Reverses a halting trace that starts from `source`: the result starts
+/// from the model the trace builds and ends with `source`, running the
+/// steps backwards with fills and voids, fissions and fusions swapped and
+/// moves negated.
+///
+/// Bids are not preserved, a fission undoing a fusion hands out the
+/// smallest seed, so bots are matched between both runs by position and
+/// the reversed trace is simulated while it's being built.
+pub fn reverse_trace(
+    source: &Matrix,
+    max_bots: u8,
+    trace: &[Command],
+) -> anyhow::Result<Vec<Command>> {
+    let mut state = State::new(max_bots, source.clone());
+    let mut frames = vec![];
+    let mut steps = vec![];
+    let mut commands = trace;
+
+    while !state.halted {
+        let count = state.current_bot_count;
+        if commands.len() < count {
+            return Err(anyhow!("Only halting traces can be reversed"));
+        }
+
+        let (step, rest) = commands.split_at(count);
+        frames.push(frame(&state));
+        steps.push(step);
+        state.step(step)?;
+        commands = rest;
+    }
+
+    // The halting step stays last, everything before it runs backwards.
+    let halt = steps.len() - 1;
+
+    let mut reversed = State::new(max_bots, state.matrix.clone());
+    let mut result = vec![];
+
+    for t in (0..halt).rev() {
+        let before = &frames[t];
+        let after = &frames[t + 1];
+
+        let step = reversed
+            .bots
+            .iter()
+            .flatten()
+            .map(|bot| {
+                let original = after
+                    .iter()
+                    .find(|original| original.position == *bot.position())
+                    .ok_or_else(|| anyhow!("Bot {} has no counterpart in step {t}", bot.index))?;
+                reverse_command(original, before, steps[t])
+            })
+            .collect::<anyhow::Result<Vec<Command>>>()?;
+
+        reversed.step(&step)?;
+        result.extend(step);
+    }
+
+    result.push(Command::Halt);
+    reversed.step(&[Command::Halt])?;
+
+    Ok(result)
+}
+
+/// Command that undoes what happened to `bot` (as seen at the end of the
+/// step) during a step starting with `before`.
+fn reverse_command(
+    bot: &FrameBot,
+    before: &[FrameBot],
+    step: &[Command],
+) -> anyhow::Result<Command> {
+    let Some(index) = before.iter().position(|other| other.bid == bot.bid) else {
+        // Created by a fission in this step, fuse back into the parent.
+        let fission = before
+            .iter()
+            .zip(step)
+            .find_map(|(parent, command)| match command {
+                Command::Fission(fission)
+                    if get_position_by_diff(&parent.position, &fission.nd) == bot.position =>
+                {
+                    Some(fission)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("Bot {} appeared without a fission", bot.bid))?;
+
+        return Ok(Command::FusionS(FusionS {
+            nd: fission.nd.negated(),
+        }));
+    };
+
+    Ok(match &step[index] {
+        Command::Halt => return Err(anyhow!("Halt in the middle of a trace")),
+        Command::Wait => Command::Wait,
+        Command::Flip => Command::Flip,
+        Command::SMove(m) => Command::SMove(SMove {
+            lld: m.lld.negated(),
+        }),
+        Command::LMove(m) => Command::LMove(LMove {
+            sld1: m.sld2.negated(),
+            sld2: m.sld1.negated(),
+        }),
+        Command::Fission(fission) => Command::FusionP(FusionP {
+            nd: fission.nd.clone(),
+        }),
+        Command::FusionP(fusion) => {
+            let secondary = get_position_by_diff(&before[index].position, &fusion.nd);
+            let seeds = before
+                .iter()
+                .find(|other| other.position == secondary)
+                .map(|other| other.seeds)
+                .ok_or_else(|| anyhow!("FusionP of bot {} has no secondary", bot.bid))?;
+
+            Command::Fission(Fission {
+                nd: fusion.nd.clone(),
+                m: seeds as u8,
+            })
+        }
+        Command::FusionS(_) => return Err(anyhow!("Bot {} fused away", bot.bid)),
+        Command::Fill(fill) => Command::Void(Void {
+            nd: fill.nd.clone(),
+        }),
+        Command::Void(void) => Command::Fill(Fill {
+            nd: void.nd.clone(),
+        }),
+        Command::GFill(fill) => Command::GVoid(GVoid {
+            nd: fill.nd.clone(),
+            fd: fill.fd.clone(),
+        }),
+        Command::GVoid(void) => Command::GFill(GFill {
+            nd: void.nd.clone(),
+            fd: void.fd.clone(),
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use commands::Difference;
+    use mdl::CellState;
+
+    #[test]
+    fn test_reverse_relabels_fission() -> anyhow::Result<()> {
+        let fission = |dx, dy, dz, m| {
+            Command::Fission(Fission {
+                nd: Difference::near(dx, dy, dz),
+                m,
+            })
+        };
+        let fusion_p = |dx, dy, dz| {
+            Command::FusionP(FusionP {
+                nd: Difference::near(dx, dy, dz),
+            })
+        };
+        let fusion_s = |dx, dy, dz| {
+            Command::FusionS(FusionS {
+                nd: Difference::near(dx, dy, dz),
+            })
+        };
+
+        // Bot 2 takes seed 3 and later absorbs bot 4, undoing that fusion
+        // spawns bot 3 in place of bot 4.
+        let trace = vec![
+            fission(1, 0, 0, 1),
+            fission(0, 0, 1, 0),
+            Command::Wait,
+            Command::Wait,
+            fusion_p(-1, 0, 1),
+            fusion_s(1, 0, -1),
+            Command::Fill(Fill {
+                nd: Difference::near(1, 0, 1),
+            }),
+            Command::Wait,
+            fusion_p(1, 0, 0),
+            fusion_s(-1, 0, 0),
+            Command::Halt,
+        ];
+
+        let mut expected = Matrix::new(4);
+        expected.set(1, 0, 1, CellState::Fill);
+
+        let reversed = reverse_trace(&Matrix::new(4), 4, &trace)?;
+        let mut state = State::new(4, expected);
+        state.execute(&reversed)?;
+
+        assert!(state.halted);
+        assert!(state.matrix.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::anyhow;
+use bot::Position;
+use commands::{Command, Difference};
+
+/// Position at `d` from `position`, failing instead of wrapping around when
+/// it leaves the coordinate space.
+pub fn offset(position: &Position, d: &Difference) -> anyhow::Result<Position> {
+    let coordinate = |value: u8, d: i8| {
+        u8::try_from(value as i16 + d as i16)
+            .map_err(|_| anyhow!("{position:?} moved by {d:?} leaves the space"))
+    };
+
+    Ok(Position::new(
+        coordinate(position.x, d.dx)?,
+        coordinate(position.y, d.dy)?,
+        coordinate(position.z, d.dz)?,
+    ))
+}
+
+/// Cells a bot at `position` occupies while running `command`. Moves list
+/// the cells in the order they are swept, so the last one is where the bot
+/// ends up. Group commands only claim the bot's own cell here, their region
+/// is shared by the whole group.
+pub fn volatile(position: &Position, command: &Command) -> anyhow::Result<Vec<Position>> {
+    let mut cells = vec![position.clone()];
+
+    match command {
+        Command::Halt
+        | Command::Wait
+        | Command::Flip
+        | Command::FusionP(_)
+        | Command::FusionS(_)
+        | Command::GFill(_)
+        | Command::GVoid(_) => {}
+        Command::SMove(m) => sweep(&mut cells, &m.lld)?,
+        Command::LMove(m) => {
+            sweep(&mut cells, &m.sld1)?;
+            sweep(&mut cells, &m.sld2)?;
+        }
+        Command::Fission(fission) => cells.push(offset(position, &fission.nd)?),
+        Command::Fill(fill) => cells.push(offset(position, &fill.nd)?),
+        Command::Void(void) => cells.push(offset(position, &void.nd)?),
+    }
+
+    Ok(cells)
+}
+
+/// Appends every cell a linear move passes through, one voxel at a time.
+fn sweep(cells: &mut Vec<Position>, d: &Difference) -> anyhow::Result<()> {
+    let unit = Difference::near(d.dx.signum(), d.dy.signum(), d.dz.signum());
+    for _ in 0..d.dx.abs() + d.dy.abs() + d.dz.abs() {
+        let next = offset(cells.last().unwrap(), &unit)?;
+        cells.push(next);
+    }
+    Ok(())
+}
@@ -12,14 +12,19 @@ impl Plugin for CubePlugin {
 #[derive(Component)]
 pub struct Cube(pub usize, pub usize, pub usize);
 
+/// Color of a `Cube`, blue when it has none.
+#[derive(Component)]
+pub struct CubeColor(pub Color);
+
 fn render_cube(
     mut commands: Commands,
-    query: Query<(Entity, &Cube), Added<Cube>>,
+    query: Query<(Entity, &Cube, Option<&CubeColor>), Added<Cube>>,
     mut instancing: Query<(Entity, &mut InstanceMaterialData)>,
 ) {
     let mut instance = instancing.get_single_mut().unwrap();
 
-    for (entity, cube) in query.iter() {
+    for (entity, cube, color) in query.iter() {
+        let color = color.map_or(Color::srgb(0.2, 0.4, 0.8), |color| color.0);
         instance.1.push(InstanceData {
             position: Vec3::new(
                 1.0 * cube.0 as f32,
@@ -27,7 +32,7 @@ fn render_cube(
                 1.0 * cube.2 as f32,
             ),
             scale: 1.0,
-            color: LinearRgba::from(Color::srgb(0.2, 0.4, 0.8)).to_f32_array(),
+            color: LinearRgba::from(color).to_f32_array(),
         });
 
         commands.entity(entity).despawn();
@@ -8,7 +8,7 @@ use crate::camera::CameraPlugin;
 use crate::cube::CubePlugin;
 use crate::instancing::InstancingPlugin;
 use crate::model::{LoadModelEvent, ModelPlugin, RenderModelEvent, SelectedModelState};
-use crate::trace::{LoadTraceEvent, TracePlugin};
+use crate::trace::{LoadTraceEvent, RunTraceEvent, SelectedTraceState, TracePlugin};
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::prelude::*;
 use bevy::window::WindowResolution;
@@ -83,7 +83,9 @@ fn ui_system(
     mut ev_load_model: EventWriter<LoadModelEvent>,
     mut ev_load_trace: EventWriter<LoadTraceEvent>,
     mut ev_render_trace: EventWriter<RenderModelEvent>,
+    mut ev_run_trace: EventWriter<RunTraceEvent>,
     model: Query<(Entity, &SelectedModelState)>,
+    trace: Query<&SelectedTraceState>,
 ) {
     let ctx = contexts.ctx_mut();
     egui::SidePanel::left("left_panel")
@@ -151,8 +153,14 @@ fn ui_system(
 
                 ui.separator();
 
-                if ui.button("Run trace").clicked() {
-                    println!("Run trace");
+                let has_trace = trace.get_single().is_ok_and(|x| x.data.is_some());
+
+                // Highlights where the trace's result differs from the model.
+                if ui
+                    .add_enabled(has_model && has_trace, egui::Button::new("Run trace"))
+                    .clicked()
+                {
+                    ev_run_trace.send(RunTraceEvent);
                 }
 
                 if ui.button("Clear trace").clicked() {
@@ -47,7 +47,7 @@ impl Plugin for ModelPlugin {
 
 pub struct ModelData {
     path: String,
-    matrix: Matrix,
+    pub matrix: Matrix,
     elapsed: Duration,
 }
 
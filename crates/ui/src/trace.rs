@@ -1,31 +1,71 @@
+use crate::cube::{Cube, CubeColor};
+use crate::instancing::InstanceMaterialData;
+use crate::model::SelectedModelState;
 use bevy::prelude::*;
 use bevy::tasks::{AsyncComputeTaskPool, Task};
 use commands::Command;
 use futures_lite::future;
+use mdl::{CellState, Diff, Matrix, MismatchKind};
 use rfd::FileDialog;
+use state::State;
 use std::fs::File;
 use std::io::BufReader;
 use std::time::{Duration, Instant};
 
+/// Bots available to traces of the full contest.
+const MAX_BOTS: u8 = 40;
+/// Voxels of the model the trace doesn't fill.
+const MISSING: Color = Color::srgb(0.9, 0.8, 0.2);
+/// Voxels the trace fills outside the model.
+const EXTRA: Color = Color::srgb(0.9, 0.2, 0.2);
+
 #[derive(Event)]
 pub struct LoadTraceEvent;
 
+#[derive(Event)]
+pub struct RunTraceEvent;
+
 #[derive(Component)]
 struct TraceFileSelectionTask(Task<Option<TraceData>>);
 
-struct TraceData {
+#[derive(Component)]
+struct TraceRunTask(Task<TraceRun>);
+
+/// Voxels a trace builds, split by how they compare with the model.
+struct TraceRun {
+    path: String,
+    matched: Vec<[usize; 3]>,
+    mismatches: Vec<([usize; 3], MismatchKind)>,
+    status: String,
+}
+
+pub struct TraceData {
     path: String,
     commands: Vec<Command>,
     elapsed: Duration,
 }
 
+#[derive(Component, Default)]
+pub struct SelectedTraceState {
+    pub data: Option<TraceData>,
+}
+
 pub struct TracePlugin;
 
 impl Plugin for TracePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<LoadTraceEvent>();
+        app.add_event::<RunTraceEvent>();
         app.add_systems(Startup, setup);
-        app.add_systems(Update, (listen_load_trace_events, poll_model_select));
+        app.add_systems(
+            Update,
+            (
+                listen_load_trace_events,
+                listen_run_trace_events,
+                poll_model_select,
+                poll_trace_run,
+            ),
+        );
     }
 }
 
@@ -33,6 +73,8 @@ impl Plugin for TracePlugin {
 struct TraceStatsText;
 
 fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn(SelectedTraceState::default());
+
     commands.spawn((
         TraceStatsText,
         TextBundle {
@@ -84,9 +126,96 @@ fn listen_load_trace_events(mut commands: Commands, mut events: EventReader<Load
     });
 }
 
+/// Runs `commands` from an empty model of the resolution of `model` and
+/// compares what they build with it.
+fn run_trace(path: String, commands: Vec<Command>, model: Matrix) -> TraceRun {
+    let mut state = State::new(MAX_BOTS, Matrix::new(model.r));
+    let result = state.execute(&commands);
+    let mismatches = state.matrix.mismatches(&model).collect::<Vec<_>>();
+    let diff = mismatches.iter().copied().collect::<Diff>();
+    let matched = state
+        .matrix
+        .iter()
+        .filter(|cell| cell.state == CellState::Fill && model.is_filled(cell.x, cell.y, cell.z))
+        .map(|cell| [cell.x, cell.y, cell.z])
+        .collect();
+
+    let status = match result {
+        Ok(()) if diff.is_empty() => "builds the model".to_string(),
+        Ok(()) => format!("differs from the model, {diff}"),
+        Err(e) => format!("fails at step {}: {e}, {diff}", state.steps),
+    };
+    TraceRun {
+        path,
+        matched,
+        mismatches,
+        status,
+    }
+}
+
+fn listen_run_trace_events(
+    mut commands: Commands,
+    mut events: EventReader<RunTraceEvent>,
+    model: Query<&SelectedModelState>,
+    trace: Query<&SelectedTraceState>,
+) {
+    for _ in events.read() {
+        let (Ok(model), Ok(trace)) = (model.get_single(), trace.get_single()) else {
+            continue;
+        };
+        let (Some(model), Some(trace)) = (&model.data, &trace.data) else {
+            continue;
+        };
+
+        let (path, trace, matrix) = (
+            trace.path.clone(),
+            trace.commands.clone(),
+            model.matrix.clone(),
+        );
+        let thread_pool = AsyncComputeTaskPool::get();
+        let task = thread_pool.spawn(async move { run_trace(path, trace, matrix) });
+        commands.spawn(TraceRunTask(task));
+    }
+}
+
+/// Renders what a finished trace run builds, missing and extra voxels
+/// highlighted.
+fn poll_trace_run(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut TraceRunTask)>,
+    mut instancing: Query<&mut InstanceMaterialData>,
+    mut trace_stat_text: Query<&mut Text, With<TraceStatsText>>,
+) {
+    for (entity, mut task) in tasks.iter_mut() {
+        let Some(run) = future::block_on(future::poll_once(&mut task.0)) else {
+            continue;
+        };
+        commands.entity(entity).despawn();
+
+        if let Ok(mut instances) = instancing.get_single_mut() {
+            instances.0.clear();
+        }
+        for [x, y, z] in run.matched {
+            commands.spawn(Cube(x, y, z));
+        }
+        for ([x, y, z], kind) in run.mismatches {
+            let color = match kind {
+                MismatchKind::Missing => MISSING,
+                MismatchKind::Extra => EXTRA,
+            };
+            commands.spawn((Cube(x, y, z), CubeColor(color)));
+        }
+
+        for mut text in &mut trace_stat_text {
+            text.sections[0].value = format!("Trace {} {}", run.path, run.status);
+        }
+    }
+}
+
 fn poll_model_select(
     mut commands: Commands,
     mut tasks: Query<(Entity, &mut TraceFileSelectionTask)>,
+    mut trace: Query<&mut SelectedTraceState>,
     mut model_stat_text: Query<&mut Text, With<TraceStatsText>>,
 ) {
     for (entity, mut selected_file) in tasks.iter_mut() {
@@ -101,6 +230,10 @@ fn poll_model_select(
                         data.elapsed
                     );
                 }
+
+                if let Ok(mut trace) = trace.get_single_mut() {
+                    trace.data = Some(data);
+                }
             }
         }
     }